@@ -1,17 +1,26 @@
 //! Animated WebP encoding and decoding.
 
+use crate::compositor::{scan_frames, Compositor, FrameMeta};
 use crate::config::{EncoderConfig, Preset};
-use crate::error::{Error, Result};
-use crate::types::{ColorMode, EncodePixel, PixelLayout};
+use crate::demux::FrameIter;
+use crate::demux::{BlendMethod, DisposeMethod};
+use crate::error::{EncodingError, Error, Result};
+use crate::types::{BitstreamFormat, ColorMode, EncodePixel, PixelLayout, YuvPlanesRef};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+#[cfg(feature = "icc")]
+use core::ops::Range;
 use core::ptr;
+use enough::{Stop, Unstoppable};
+use imgref::ImgRef;
 use whereat::*;
 
 /// A single frame in an animation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 #[non_exhaustive]
 pub struct Frame {
-    /// Frame pixel data (RGBA).
+    /// Frame pixel data (RGBA unless another color mode was requested).
     pub data: Vec<u8>,
     /// Frame width.
     pub width: u32,
@@ -23,6 +32,39 @@ pub struct Frame {
     pub duration_ms: u32,
 }
 
+/// A decoded frame borrowing the decoder's canvas.
+///
+/// Returned by [`AnimationDecoder::next_frame_ref`]. The pixel data is only
+/// valid until the decoder is used again; call
+/// [`to_frame`](Self::to_frame) to keep it.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct FrameRef<'a> {
+    /// Frame pixel data (RGBA unless another color mode was requested).
+    pub data: &'a [u8],
+    /// Frame width.
+    pub width: u32,
+    /// Frame height.
+    pub height: u32,
+    /// Frame timestamp in milliseconds from animation start.
+    pub timestamp_ms: i32,
+    /// Frame duration in milliseconds.
+    pub duration_ms: u32,
+}
+
+impl FrameRef<'_> {
+    /// Copy the frame into an owned [`Frame`].
+    pub fn to_frame(&self) -> Frame {
+        Frame {
+            data: self.data.to_vec(),
+            width: self.width,
+            height: self.height,
+            timestamp_ms: self.timestamp_ms,
+            duration_ms: self.duration_ms,
+        }
+    }
+}
+
 /// Animation metadata.
 #[derive(Debug, Clone)]
 #[non_exhaustive]
@@ -59,18 +101,151 @@ pub struct AnimationInfo {
 /// }
 /// # Ok::<(), webpx::At<webpx::Error>>(())
 /// ```
-pub struct AnimationDecoder {
-    decoder: *mut libwebp_sys::WebPAnimDecoder,
+///
+/// The decoder is also an [`Iterator`] over `Result<Frame>`. For long
+/// playback, [`next_frame_ref`](Self::next_frame_ref) and
+/// [`next_frame_into`](Self::next_frame_into) avoid allocating a buffer
+/// per frame.
+pub struct AnimationDecoder<'a> {
     info: AnimationInfo,
-    _data: Vec<u8>, // Keep data alive
+    frames: Vec<FrameMeta>,
+    /// Allocates its canvas on first use, so limits can reject a file
+    /// before any frame buffer exists.
+    compositor: Compositor,
+    next_index: usize,
+    limits: AnimationLimits,
+    decoded_bytes: u64,
+    resize: Option<Resize>,
+    /// Metadata chunks, as ranges into the input.
+    #[cfg(feature = "icc")]
+    icc_profile: Option<Range<usize>>,
+    #[cfg(feature = "icc")]
+    exif: Option<Range<usize>>,
+    #[cfg(feature = "icc")]
+    xmp: Option<Range<usize>>,
+    data: InputData<'a>,
+}
+
+/// Crop and scale applied by an [`AnimationDecoder`] to composited frames.
+struct Resize {
+    /// Region of the canvas to keep: left, top, width and height.
+    crop: Option<(u32, u32, u32, u32)>,
+    /// Requested output size, before deriving a 0 dimension.
+    scale: Option<(u32, u32)>,
+    width: u32,
+    height: u32,
+    /// Premultiply alpha after scaling, for premultiplied color modes.
+    /// Cropping alone keeps compositing in the premultiplied mode.
+    premultiply: bool,
+    data: Vec<u8>,
+}
+
+/// Input bytes of an [`AnimationDecoder`], borrowed or owned.
+enum InputData<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Box<dyn AsRef<[u8]> + Send + 'a>),
+}
+
+impl InputData<'_> {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            InputData::Borrowed(data) => data,
+            InputData::Owned(data) => (**data).as_ref(),
+        }
+    }
+}
+
+/// Resource limits for [`AnimationDecoder`].
+///
+/// All limits are disabled by default.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::{AnimationDecoder, AnimationLimits};
+///
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// let limits = AnimationLimits::new()
+///     .max_canvas_pixels(4096 * 4096)
+///     .max_frame_count(1000)
+///     .max_total_bytes(512 * 1024 * 1024);
+///
+/// let mut decoder = AnimationDecoder::new(webp_data)?;
+/// decoder.set_limits(limits)?; // rejects oversized canvases or frame counts
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct AnimationLimits {
+    pub(crate) max_canvas_pixels: Option<u64>,
+    pub(crate) max_frame_count: Option<u32>,
+    pub(crate) max_total_bytes: Option<u64>,
 }
 
-// SAFETY: WebPAnimDecoder is thread-safe for single-threaded access
-unsafe impl Send for AnimationDecoder {}
+impl AnimationLimits {
+    /// Create limits with everything disabled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum canvas size in pixels (width * height).
+    #[must_use]
+    pub fn max_canvas_pixels(mut self, pixels: u64) -> Self {
+        self.max_canvas_pixels = Some(pixels);
+        self
+    }
+
+    /// Maximum number of frames.
+    #[must_use]
+    pub fn max_frame_count(mut self, frames: u32) -> Self {
+        self.max_frame_count = Some(frames);
+        self
+    }
+
+    /// Maximum number of decoded output bytes across all frames of one pass.
+    ///
+    /// Every composited frame counts as a full canvas, and a frame returned
+    /// [cropped](AnimationDecoder::set_crop) or
+    /// [scaled](AnimationDecoder::set_scale) also counts its output size.
+    /// The count restarts on [`AnimationDecoder::reset`].
+    #[must_use]
+    pub fn max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
 
-impl AnimationDecoder {
+    /// Check an animation header against the canvas and frame count limits.
+    fn check_info(&self, info: &AnimationInfo) -> Result<()> {
+        let pixels = info.width as u64 * info.height as u64;
+        if let Some(max) = self.max_canvas_pixels {
+            if pixels > max {
+                return Err(at!(Error::InvalidInput(alloc::format!(
+                    "canvas {}x{} exceeds limit of {} pixels",
+                    info.width,
+                    info.height,
+                    max
+                ))));
+            }
+        }
+        if let Some(max) = self.max_frame_count {
+            if info.frame_count > max {
+                return Err(at!(Error::InvalidInput(alloc::format!(
+                    "{} frames exceeds limit of {} frames",
+                    info.frame_count,
+                    max
+                ))));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> AnimationDecoder<'a> {
     /// Create a new animation decoder.
-    pub fn new(data: &[u8]) -> Result<Self> {
+    ///
+    /// The input is borrowed, not copied; it must outlive the decoder.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
         Self::with_options(data, ColorMode::Rgba, true)
     }
 
@@ -79,67 +254,68 @@ impl AnimationDecoder {
     /// # Arguments
     ///
     /// * `data` - WebP animation data
-    /// * `color_mode` - Output color format
+    /// * `color_mode` - Output color format: [`ColorMode::Rgba`], [`ColorMode::Bgra`]
+    ///   or their premultiplied variants. Other modes, including YUV, are
+    ///   rejected, since frames are composited on an RGBA or BGRA canvas.
     /// * `use_threads` - Enable multi-threaded decoding
-    pub fn with_options(data: &[u8], color_mode: ColorMode, use_threads: bool) -> Result<Self> {
+    pub fn with_options(data: &'a [u8], color_mode: ColorMode, use_threads: bool) -> Result<Self> {
+        Self::from_input(InputData::Borrowed(data), color_mode, use_threads)
+    }
+
+    fn from_input(data: InputData<'a>, color_mode: ColorMode, use_threads: bool) -> Result<Self> {
         let csp_mode = match color_mode {
             ColorMode::Rgba => libwebp_sys::WEBP_CSP_MODE::MODE_RGBA,
             ColorMode::Bgra => libwebp_sys::WEBP_CSP_MODE::MODE_BGRA,
-            ColorMode::Argb => libwebp_sys::WEBP_CSP_MODE::MODE_ARGB,
-            ColorMode::Rgb => libwebp_sys::WEBP_CSP_MODE::MODE_RGB,
-            ColorMode::Bgr => libwebp_sys::WEBP_CSP_MODE::MODE_BGR,
+            ColorMode::RgbaPremultiplied => libwebp_sys::WEBP_CSP_MODE::MODE_rgbA,
+            ColorMode::BgraPremultiplied => libwebp_sys::WEBP_CSP_MODE::MODE_bgrA,
             _ => {
                 return Err(at!(Error::InvalidInput(
-                    "animation decoder only supports RGB modes".into(),
+                    "animation decoder only supports RGBA and BGRA output".into(),
                 )))
             }
         };
 
-        // Keep a copy of the data since WebPAnimDecoder references it
-        let data_copy = data.to_vec();
-
-        let mut options = core::mem::MaybeUninit::<libwebp_sys::WebPAnimDecoderOptions>::uninit();
-        let ok = unsafe { libwebp_sys::WebPAnimDecoderOptionsInit(options.as_mut_ptr()) };
-        if ok == 0 {
-            return Err(at!(Error::InvalidConfig(
-                "failed to init decoder options".into(),
-            )));
-        }
-        let mut options = unsafe { options.assume_init() };
-
-        options.color_mode = csp_mode;
-        options.use_threads = use_threads as i32;
-
-        let webp_data = libwebp_sys::WebPData {
-            bytes: data_copy.as_ptr(),
-            size: data_copy.len(),
+        // Only parse the container here; frames are decoded on demand
+        let iter = FrameIter::new(data.as_slice())?;
+        let demuxer = iter.demuxer();
+        let info = AnimationInfo {
+            width: demuxer.canvas_width(),
+            height: demuxer.canvas_height(),
+            frame_count: demuxer.frame_count(),
+            loop_count: demuxer.loop_count(),
+            bgcolor: demuxer.bgcolor(),
         };
-
-        let decoder = unsafe { libwebp_sys::WebPAnimDecoderNew(&webp_data, &options) };
-
-        if decoder.is_null() {
-            return Err(at!(Error::InvalidWebP));
-        }
-
-        // Get animation info
-        let mut anim_info = libwebp_sys::WebPAnimInfo::default();
-        let ok = unsafe { libwebp_sys::WebPAnimDecoderGetInfo(decoder, &mut anim_info) };
-
-        if ok == 0 {
-            unsafe { libwebp_sys::WebPAnimDecoderDelete(decoder) };
-            return Err(at!(Error::InvalidWebP));
-        }
+        #[cfg(feature = "icc")]
+        let chunk_range = |fourcc: &[u8; 4]| {
+            let base = data.as_slice().as_ptr() as usize;
+            demuxer.chunk(fourcc).map(|chunk| {
+                let offset = chunk.as_ptr() as usize - base;
+                offset..offset + chunk.len()
+            })
+        };
+        #[cfg(feature = "icc")]
+        let (icc_profile, exif, xmp) = (
+            chunk_range(b"ICCP"),
+            chunk_range(b"EXIF"),
+            chunk_range(b"XMP "),
+        );
+        let frames = scan_frames(iter, data.as_slice());
 
         Ok(Self {
-            decoder,
-            info: AnimationInfo {
-                width: anim_info.canvas_width,
-                height: anim_info.canvas_height,
-                frame_count: anim_info.frame_count,
-                loop_count: anim_info.loop_count,
-                bgcolor: anim_info.bgcolor,
-            },
-            _data: data_copy,
+            compositor: Compositor::new(info.width, info.height, csp_mode, use_threads),
+            info,
+            frames,
+            next_index: 0,
+            limits: AnimationLimits::default(),
+            decoded_bytes: 0,
+            resize: None,
+            #[cfg(feature = "icc")]
+            icc_profile,
+            #[cfg(feature = "icc")]
+            exif,
+            #[cfg(feature = "icc")]
+            xmp,
+            data,
         })
     }
 
@@ -148,94 +324,1092 @@ impl AnimationDecoder {
         &self.info
     }
 
+    /// Get the embedded ICC profile, if any.
+    #[cfg(feature = "icc")]
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.chunk(&self.icc_profile)
+    }
+
+    /// Get the embedded EXIF metadata, if any.
+    #[cfg(feature = "icc")]
+    pub fn exif(&self) -> Option<&[u8]> {
+        self.chunk(&self.exif)
+    }
+
+    /// Get the embedded XMP metadata, if any.
+    #[cfg(feature = "icc")]
+    pub fn xmp(&self) -> Option<&[u8]> {
+        self.chunk(&self.xmp)
+    }
+
+    #[cfg(feature = "icc")]
+    fn chunk(&self, range: &Option<Range<usize>>) -> Option<&[u8]> {
+        self.data.as_slice().get(range.clone()?)
+    }
+
+    /// Apply resource limits.
+    ///
+    /// Canvas size and frame count are checked immediately, before any frame
+    /// buffers are allocated. The total decoded bytes limit is enforced by
+    /// each subsequent [`next_frame`](Self::next_frame).
+    pub fn set_limits(&mut self, limits: AnimationLimits) -> Result<()> {
+        limits.check_info(&self.info)?;
+        self.limits = limits;
+        Ok(())
+    }
+
+    /// Iterate over the raw, uncomposited frames.
+    ///
+    /// See [`FrameIter`] for details. This does not affect the position of
+    /// [`next_frame`](Self::next_frame).
+    pub fn raw_frames(&self) -> Result<FrameIter<'_>> {
+        FrameIter::new(self.data.as_slice())
+    }
+
     /// Check if there are more frames to decode.
     pub fn has_more_frames(&self) -> bool {
-        unsafe { libwebp_sys::WebPAnimDecoderHasMoreFrames(self.decoder) != 0 }
+        self.next_index < self.frames.len()
     }
 
     /// Decode the next frame.
     ///
     /// Returns `None` when all frames have been decoded.
     pub fn next_frame(&mut self) -> Result<Option<Frame>> {
+        self.next_frame_stoppable(Unstoppable)
+    }
+
+    /// Decode the next frame with cooperative cancellation.
+    ///
+    /// `stop` is checked before each frame is decoded.
+    ///
+    /// Returns `None` when all frames have been decoded.
+    pub fn next_frame_stoppable(&mut self, stop: impl Stop) -> Result<Option<Frame>> {
         if !self.has_more_frames() {
             return Ok(None);
         }
+        let index = self.next_index;
+        self.render(index, &stop)?;
+        self.apply_resize()?;
+        Ok(Some(self.current_frame(index)))
+    }
+
+    /// Decode the next frame without copying it out of the decoder.
+    ///
+    /// The returned [`FrameRef`] borrows the decoder's canvas, which is
+    /// overwritten by the next call.
+    ///
+    /// Returns `None` when all frames have been decoded.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use webpx::AnimationDecoder;
+    ///
+    /// fn upload_texture(_rgba: &[u8]) {}
+    ///
+    /// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+    /// let mut decoder = AnimationDecoder::new(webp_data)?;
+    /// while let Some(frame) = decoder.next_frame_ref()? {
+    ///     upload_texture(frame.data);
+    /// }
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn next_frame_ref(&mut self) -> Result<Option<FrameRef<'_>>> {
+        if !self.has_more_frames() {
+            return Ok(None);
+        }
+        let index = self.next_index;
+        self.render(index, Unstoppable)?;
+        self.apply_resize()?;
+        Ok(Some(self.frame_ref(index)))
+    }
+
+    /// Decode the next frame into `frame`, reusing its pixel buffer.
+    ///
+    /// Once `frame.data` has grown to the canvas size, no further
+    /// allocations are made. Returns `false` (leaving `frame` untouched)
+    /// when all frames have been decoded.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use webpx::{AnimationDecoder, Frame};
+    ///
+    /// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+    /// let mut decoder = AnimationDecoder::new(webp_data)?;
+    /// let mut frame = Frame::default();
+    /// while decoder.next_frame_into(&mut frame)? {
+    ///     println!("{} ms", frame.timestamp_ms);
+    /// }
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn next_frame_into(&mut self, frame: &mut Frame) -> Result<bool> {
+        let Some(frame_ref) = self.next_frame_ref()? else {
+            return Ok(false);
+        };
+        frame.data.clear();
+        frame.data.extend_from_slice(frame_ref.data);
+        frame.width = frame_ref.width;
+        frame.height = frame_ref.height;
+        frame.timestamp_ms = frame_ref.timestamp_ms;
+        frame.duration_ms = frame_ref.duration_ms;
+        Ok(true)
+    }
 
-        let mut buf: *mut u8 = ptr::null_mut();
-        let mut timestamp: i32 = 0;
+    /// Decode the frame at `index` (0-based).
+    ///
+    /// Decoding restarts at the nearest keyframe at or before `index` (a
+    /// frame that does not depend on earlier canvas state), or continues
+    /// from the current position if that is closer. Afterwards,
+    /// [`next_frame`](Self::next_frame) returns the frame after `index`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use webpx::AnimationDecoder;
+    ///
+    /// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+    /// let mut decoder = AnimationDecoder::new(webp_data)?;
+    /// let poster = decoder.seek_to_frame(10)?;
+    /// let at_two_seconds = decoder.seek_to_time(2000)?;
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn seek_to_frame(&mut self, index: u32) -> Result<Frame> {
+        self.seek_to_frame_stoppable(index, Unstoppable)
+    }
 
-        let ok =
-            unsafe { libwebp_sys::WebPAnimDecoderGetNext(self.decoder, &mut buf, &mut timestamp) };
+    /// Decode the frame at `index` with cooperative cancellation.
+    ///
+    /// `stop` is checked before each frame is composited on the way to
+    /// `index`, which can be every earlier frame if the animation has no
+    /// keyframes.
+    pub fn seek_to_frame_stoppable(&mut self, index: u32, stop: impl Stop) -> Result<Frame> {
+        let index = index as usize;
+        if index >= self.frames.len() {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "frame index {} out of range ({} frames)",
+                index,
+                self.frames.len()
+            ))));
+        }
 
-        if ok == 0 {
-            return Err(at!(Error::DecodeFailed(
-                crate::error::DecodingError::BitstreamError,
+        let keyframe = self.frames[..=index]
+            .iter()
+            .rposition(|frame| frame.keyframe)
+            .unwrap_or(0);
+        let start = if self.next_index > keyframe && self.next_index <= index {
+            self.next_index
+        } else {
+            keyframe
+        };
+
+        for i in start..=index {
+            self.render(i, &stop)?;
+        }
+        self.apply_resize()?;
+        Ok(self.current_frame(index))
+    }
+
+    /// Decode the frame displayed at `timestamp_ms` from the animation start.
+    ///
+    /// Times past the end select the last frame. See
+    /// [`seek_to_frame`](Self::seek_to_frame) for how decoding resumes.
+    pub fn seek_to_time(&mut self, timestamp_ms: i32) -> Result<Frame> {
+        self.seek_to_time_stoppable(timestamp_ms, Unstoppable)
+    }
+
+    /// Decode the frame displayed at `timestamp_ms` with cooperative
+    /// cancellation. See [`seek_to_frame_stoppable`](Self::seek_to_frame_stoppable).
+    pub fn seek_to_time_stoppable(&mut self, timestamp_ms: i32, stop: impl Stop) -> Result<Frame> {
+        let index = self
+            .frames
+            .iter()
+            .position(|frame| frame.end_ms() > timestamp_ms)
+            .unwrap_or(self.frames.len().saturating_sub(1));
+        self.seek_to_frame_stoppable(index as u32, stop)
+    }
+
+    /// Composite frame `index` onto the canvas, checking `stop` and limits.
+    fn render(&mut self, index: usize, stop: impl Stop) -> Result<()> {
+        stop.check().map_err(|reason| at!(Error::Stopped(reason)))?;
+
+        let size = self.compositor.canvas_size() as u64;
+        self.check_total_bytes(size)?;
+        self.compositor
+            .render(self.data.as_slice(), &self.frames, index)?;
+        self.decoded_bytes += size;
+        self.next_index = index + 1;
+        Ok(())
+    }
+
+    /// Check that decoding `size` more bytes stays within the limit.
+    fn check_total_bytes(&self, size: u64) -> Result<()> {
+        if let Some(max) = self.limits.max_total_bytes {
+            if self.decoded_bytes + size > max {
+                return Err(at!(Error::InvalidInput(alloc::format!(
+                    "decoded frames exceed limit of {} bytes",
+                    max
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Crop every frame to a rectangle of the canvas.
+    ///
+    /// Frames are still composited at full size, then cropped and, if
+    /// [`set_scale`](Self::set_scale) is used, scaled. Resets the decoder to
+    /// the first frame.
+    pub fn set_crop(&mut self, left: u32, top: u32, width: u32, height: u32) -> Result<()> {
+        let fits =
+            |start: u32, len: u32, max: u32| start.checked_add(len).is_some_and(|end| end <= max);
+        if width == 0
+            || height == 0
+            || !fits(left, width, self.info.width)
+            || !fits(top, height, self.info.height)
+        {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "crop {}x{} at ({}, {}) is empty or exceeds the {}x{} canvas",
+                width,
+                height,
+                left,
+                top,
+                self.info.width,
+                self.info.height
+            ))));
+        }
+        let scale = self.resize.as_ref().and_then(|resize| resize.scale);
+        self.set_resize(Some((left, top, width, height)), scale)
+    }
+
+    /// Scale every frame to `width` x `height` with libwebp's rescaler.
+    ///
+    /// If one dimension is 0, it is derived from the other to keep the
+    /// aspect ratio of the canvas, or of the [crop](Self::set_crop)
+    /// rectangle. Frames are still composited at full size, so scaling
+    /// does not change how they are blended. Resets the decoder to the
+    /// first frame.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use webpx::AnimationDecoder;
+    ///
+    /// let sticker: &[u8] = &[0u8; 100]; // placeholder
+    /// let mut decoder = AnimationDecoder::new(sticker)?;
+    /// decoder.set_scale(96, 0)?;
+    /// for frame in decoder {
+    ///     let thumbnail = frame?; // 96 pixels wide
+    /// }
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn set_scale(&mut self, width: u32, height: u32) -> Result<()> {
+        if width == 0 && height == 0 {
+            return Err(at!(Error::InvalidInput(
+                "scaled width and height cannot both be 0".into()
             )));
         }
+        let crop = self.resize.as_ref().and_then(|resize| resize.crop);
+        self.set_resize(crop, Some((width, height)))
+    }
 
-        // Copy the frame data (buffer is owned by decoder)
-        let size = (self.info.width as usize) * (self.info.height as usize) * 4;
-        let data = unsafe { core::slice::from_raw_parts(buf, size).to_vec() };
+    fn set_resize(
+        &mut self,
+        crop: Option<(u32, u32, u32, u32)>,
+        scale: Option<(u32, u32)>,
+    ) -> Result<()> {
+        let (_, _, crop_width, crop_height) =
+            crop.unwrap_or((0, 0, self.info.width, self.info.height));
+        // Same rounding as libwebp's WebPRescalerGetScaledDimensions
+        let derive = |src: u32, other: u32, other_src: u32| {
+            ((src as u64 * other as u64 + other_src as u64 / 2) / other_src as u64).max(1)
+        };
+        let (width, height) = match scale {
+            None => (crop_width, crop_height),
+            Some((0, height)) => (derive(crop_width, height, crop_height) as u32, height),
+            Some((width, 0)) => (width, derive(crop_height, width, crop_width) as u32),
+            Some(size) => size,
+        };
+        if width > 16383 || height > 16383 {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "scaled size {}x{} exceeds 16383x16383",
+                width,
+                height
+            ))));
+        }
+
+        let premultiplied = self.compositor.premultiplied()
+            || self
+                .resize
+                .as_ref()
+                .is_some_and(|resize| resize.premultiply);
+        // The rescaler needs straight alpha, but premultiplying afterwards
+        // rounds differently from libwebp, so only switch when scaling
+        let scaled = (width, height) != (crop_width, crop_height);
+        let premultiply = premultiplied && scaled;
+        if premultiplied {
+            self.compositor.set_premultiplied(!scaled);
+        }
+        self.resize = Some(Resize {
+            crop,
+            scale,
+            width,
+            height,
+            premultiply,
+            data: Vec::new(),
+        });
+        self.reset();
+        Ok(())
+    }
+
+    /// Crop and scale the canvas into the resize buffer, if enabled.
+    fn apply_resize(&mut self) -> Result<()> {
+        let Some(size) = self
+            .resize
+            .as_ref()
+            .map(|resize| resize.width as u64 * resize.height as u64 * 4)
+        else {
+            return Ok(());
+        };
+        self.check_total_bytes(size)?;
+        self.decoded_bytes += size;
+        let Some(resize) = self.resize.as_mut() else {
+            return Ok(());
+        };
+        let canvas = self.compositor.canvas();
+        let (left, top, width, height) =
+            resize
+                .crop
+                .unwrap_or((0, 0, self.info.width, self.info.height));
+
+        let stride = self.info.width as usize * 4;
+        let rows = (top..top + height).map(|y| {
+            let start = y as usize * stride + left as usize * 4;
+            &canvas[start..start + width as usize * 4]
+        });
+        resize.data.clear();
+        if (width, height) == (resize.width, resize.height) {
+            for row in rows {
+                resize.data.extend_from_slice(row);
+            }
+            return Ok(());
+        }
 
-        // Calculate duration (difference from previous frame)
-        // For first frame, we'll set duration later from next frame timestamp
-        let duration_ms = 0; // Will be calculated by caller if needed
+        // Alpha is the top byte of each pixel read as little-endian, as the
+        // rescaler expects, whichever of RGBA and BGRA the canvas holds
+        let mut argb = Vec::with_capacity(width as usize * height as usize);
+        for row in rows {
+            argb.extend(
+                row.chunks_exact(4)
+                    .map(|px| u32::from_le_bytes([px[0], px[1], px[2], px[3]])),
+            );
+        }
+        let argb = rescale_argb(argb, width, height, resize.width, resize.height)?;
+
+        resize
+            .data
+            .extend(argb.iter().flat_map(|px| px.to_le_bytes()));
+        if resize.premultiply {
+            for px in resize.data.chunks_exact_mut(4) {
+                let alpha = px[3] as u32;
+                for channel in &mut px[..3] {
+                    *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+                }
+            }
+        }
+        Ok(())
+    }
 
-        Ok(Some(Frame {
+    /// Borrow the canvas as frame `index`.
+    fn frame_ref(&self, index: usize) -> FrameRef<'_> {
+        let meta = &self.frames[index];
+        let (data, width, height) = match &self.resize {
+            Some(resize) => (resize.data.as_slice(), resize.width, resize.height),
+            None => (self.compositor.canvas(), self.info.width, self.info.height),
+        };
+        FrameRef {
             data,
-            width: self.info.width,
-            height: self.info.height,
-            timestamp_ms: timestamp,
-            duration_ms,
-        }))
+            width,
+            height,
+            timestamp_ms: meta.end_ms(),
+            duration_ms: meta.duration_ms,
+        }
+    }
+
+    /// Copy the canvas out as frame `index`.
+    fn current_frame(&self, index: usize) -> Frame {
+        self.frame_ref(index).to_frame()
     }
 
     /// Reset the decoder to the first frame.
     pub fn reset(&mut self) {
-        unsafe {
-            libwebp_sys::WebPAnimDecoderReset(self.decoder);
-        }
+        self.next_index = 0;
+        self.decoded_bytes = 0;
     }
 
     /// Decode all frames into a vector.
     pub fn decode_all(&mut self) -> Result<Vec<Frame>> {
-        self.reset();
+        self.decode_all_stoppable(Unstoppable)
+    }
 
-        let mut frames = Vec::with_capacity(self.info.frame_count as usize);
-        let mut prev_timestamp = 0i32;
+    /// Decode all frames into a vector with cooperative cancellation.
+    ///
+    /// `stop` is checked before each frame is decoded.
+    pub fn decode_all_stoppable(&mut self, stop: impl Stop) -> Result<Vec<Frame>> {
+        self.reset();
 
-        while let Some(mut frame) = self.next_frame()? {
-            // Calculate duration from timestamp difference
-            frame.duration_ms = (frame.timestamp_ms - prev_timestamp).max(0) as u32;
-            prev_timestamp = frame.timestamp_ms;
+        let mut frames = Vec::with_capacity(self.frames.len());
+        while let Some(frame) = self.next_frame_stoppable(&stop)? {
             frames.push(frame);
         }
+        Ok(frames)
+    }
+}
+
+/// Yields each remaining frame, like [`AnimationDecoder::next_frame`].
+///
+/// Iteration ends after the first error.
+impl Iterator for AnimationDecoder<'_> {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Result<Frame>> {
+        match self.next_frame() {
+            Ok(frame) => frame.map(Ok),
+            Err(e) => {
+                self.next_index = self.frames.len();
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.frames.len() - self.next_index;
+        (0, Some(remaining))
+    }
+}
+
+impl AnimationDecoder<'static> {
+    /// Create a new animation decoder that takes ownership of the input.
+    ///
+    /// Accepts `Vec<u8>`, `Arc<[u8]>`, `Box<[u8]>` or any other owner of the
+    /// bytes. The data is not copied.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::sync::Arc;
+    /// use webpx::AnimationDecoder;
+    ///
+    /// let shared: Arc<[u8]> = Arc::from(std::fs::read("sticker.webp").unwrap());
+    /// let mut decoder = AnimationDecoder::from_owned(Arc::clone(&shared))?;
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn from_owned<T: AsRef<[u8]> + Send + 'static>(data: T) -> Result<Self> {
+        Self::from_owned_with_options(data, ColorMode::Rgba, true)
+    }
+
+    /// Create a new animation decoder with options that takes ownership of the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - WebP animation data
+    /// * `color_mode` - Output color format
+    /// * `use_threads` - Enable multi-threaded decoding
+    pub fn from_owned_with_options<T: AsRef<[u8]> + Send + 'static>(
+        data: T,
+        color_mode: ColorMode,
+        use_threads: bool,
+    ) -> Result<Self> {
+        Self::from_input(InputData::Owned(Box::new(data)), color_mode, use_threads)
+    }
+}
+
+/// Extract one frame of an animation as a standalone still WebP.
+///
+/// If the frame is a keyframe covering the whole canvas, its `ALPH` and
+/// `VP8 `/`VP8L` chunks are copied without re-encoding. Otherwise the
+/// composited canvas is encoded with `config`. Metadata is not copied.
+///
+/// # Arguments
+///
+/// * `webp_data` - Animated (or still) WebP data
+/// * `index` - Frame index (0-based)
+/// * `config` - Encoder settings used when the frame must be re-encoded
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::{extract_frame, EncoderConfig};
+///
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// let poster = extract_frame(webp_data, 0, &EncoderConfig::new().quality(90.0))?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+pub fn extract_frame(webp_data: &[u8], index: u32, config: &EncoderConfig) -> Result<Vec<u8>> {
+    let iter = FrameIter::new(webp_data)?;
+    let (canvas_width, canvas_height) = (iter.canvas_width(), iter.canvas_height());
+    let frames = scan_frames(iter, webp_data);
+    let meta = frames.get(index as usize).ok_or_else(|| {
+        at!(Error::InvalidInput(alloc::format!(
+            "frame index {} out of range ({} frames)",
+            index,
+            frames.len()
+        )))
+    })?;
+
+    if meta.keyframe && meta.is_full_canvas(canvas_width, canvas_height) {
+        return Ok(crate::mux::Mux::from_data(webp_data)?
+            .frame(index)?
+            .bitstream);
+    }
+
+    let frame = AnimationDecoder::new(webp_data)?.seek_to_frame(index)?;
+    encode_canvas(&frame, config)
+}
+
+/// Timing of a frame returned by [`split_animation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FrameTiming {
+    /// Start time in milliseconds from animation start.
+    pub start_ms: i32,
+    /// Frame duration in milliseconds.
+    pub duration_ms: u32,
+}
+
+/// Split an animation into one standalone still WebP per frame.
+///
+/// Each still is the full canvas as displayed at that frame. Keyframes
+/// covering the whole canvas are copied without re-encoding, as in
+/// [`extract_frame`]; other frames are composited and re-encoded
+/// losslessly, so no quality is lost. Metadata is not copied.
+///
+/// A still image yields itself as a single frame with zero duration.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// for (i, (still, timing)) in webpx::split_animation(webp_data)?.iter().enumerate() {
+///     println!("frame {} at {}ms for {}ms: {} bytes", i, timing.start_ms, timing.duration_ms, still.len());
+/// }
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+pub fn split_animation(webp_data: &[u8]) -> Result<Vec<(Vec<u8>, FrameTiming)>> {
+    let iter = FrameIter::new(webp_data)?;
+    let (canvas_width, canvas_height) = (iter.canvas_width(), iter.canvas_height());
+    let frames = scan_frames(iter, webp_data);
+    let mux = crate::mux::Mux::from_data(webp_data)?;
+
+    let lossless = EncoderConfig::new().lossless(true).exact(true);
+    let mut decoder: Option<AnimationDecoder<'_>> = None;
+    let mut stills = Vec::with_capacity(frames.len());
+    for (index, meta) in frames.iter().enumerate() {
+        let still = if meta.keyframe && meta.is_full_canvas(canvas_width, canvas_height) {
+            mux.frame(index as u32)?.bitstream
+        } else {
+            let decoder = match decoder.as_mut() {
+                Some(decoder) => decoder,
+                None => decoder.insert(AnimationDecoder::new(webp_data)?),
+            };
+            encode_canvas(&decoder.seek_to_frame(index as u32)?, &lossless)?
+        };
+        let timing = FrameTiming {
+            start_ms: meta.start_ms,
+            duration_ms: meta.duration_ms,
+        };
+        stills.push((still, timing));
+    }
+    Ok(stills)
+}
+
+/// Frame rate and frame count limits for [`resample_animation`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::ResampleOptions;
+///
+/// // At most 15 frames per second, and no more than 100 frames
+/// let options = ResampleOptions::new().max_fps(15.0).max_frames(100);
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ResampleOptions {
+    pub(crate) max_fps: Option<f32>,
+    pub(crate) max_frames: Option<u32>,
+}
+
+impl ResampleOptions {
+    /// Create options that keep every frame.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum frame rate. Kept frames start at least `1000 / fps`
+    /// milliseconds apart, rounded down to whole milliseconds.
+    #[must_use]
+    pub fn max_fps(mut self, fps: f32) -> Self {
+        self.max_fps = Some(fps);
+        self
+    }
+
+    /// Maximum number of frames.
+    #[must_use]
+    pub fn max_frames(mut self, frames: u32) -> Self {
+        self.max_frames = Some(frames);
+        self
+    }
+
+    /// Pick the frames to keep, given each frame's start and the total
+    /// duration. The first frame is always kept.
+    fn select(&self, starts: &[i32], total_ms: i32) -> Result<Vec<usize>> {
+        let mut interval_ms = 0u64;
+        if let Some(fps) = self.max_fps {
+            if !fps.is_finite() || fps <= 0.0 {
+                return Err(at!(Error::InvalidInput(alloc::format!(
+                    "maximum frame rate must be positive, got {}",
+                    fps
+                ))));
+            }
+            interval_ms = (1000.0 / fps as f64) as u64;
+        }
+        let max_frames = self.max_frames.unwrap_or(u32::MAX) as usize;
+        if max_frames == 0 {
+            return Err(at!(Error::InvalidInput(
+                "maximum frame count must be at least 1".into()
+            )));
+        }
+        if let Some(max) = self.max_frames {
+            // Frames this far apart can't exceed `max` within the timeline
+            interval_ms = interval_ms.max((total_ms.max(0) as u64).div_ceil(max as u64));
+        }
 
-        // Set the last frame's duration (assume same as previous or default)
-        let len = frames.len();
-        if len > 0 {
-            let prev_duration = if len > 1 {
-                frames[len - 2].duration_ms
-            } else {
-                100 // Default 100ms for single frame
+        let mut keep: Vec<usize> = Vec::new();
+        for (index, &start) in starts.iter().enumerate() {
+            let due = match keep.last() {
+                None => true,
+                Some(&last) => (start as i64 - starts[last] as i64) >= interval_ms as i64,
             };
-            frames[len - 1].duration_ms = prev_duration;
+            if due {
+                if keep.len() == max_frames {
+                    break;
+                }
+                keep.push(index);
+            }
         }
+        Ok(keep)
+    }
+}
 
-        Ok(frames)
+/// Re-encode an animation with fewer frames, capping its frame rate or
+/// frame count.
+///
+/// Frames that start too soon after the previously kept frame are
+/// dropped, and their time is added to the kept frame, so the animation
+/// plays for as long as before. The decoder's crop and scale settings
+/// apply, so this can also produce small previews. Kept frames are
+/// encoded with `config`; the loop count, background color and, with the
+/// `icc` feature, metadata are carried over.
+///
+/// The decoder must output straight (not premultiplied) alpha. It is
+/// left at the position after the last kept frame.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::{resample_animation, AnimationDecoder, EncoderConfig, ResampleOptions};
+///
+/// let capture: &[u8] = &[0u8; 100]; // placeholder, 60 fps
+/// let mut decoder = AnimationDecoder::new(capture)?;
+/// let webp = resample_animation(
+///     &mut decoder,
+///     &ResampleOptions::new().max_fps(20.0),
+///     &EncoderConfig::new().quality(75.0),
+/// )?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+pub fn resample_animation(
+    decoder: &mut AnimationDecoder<'_>,
+    options: &ResampleOptions,
+    config: &EncoderConfig,
+) -> Result<Vec<u8>> {
+    let premultiplied = decoder.compositor.premultiplied()
+        || decoder
+            .resize
+            .as_ref()
+            .is_some_and(|resize| resize.premultiply);
+    let layout = match decoder.compositor.mode() {
+        libwebp_sys::WEBP_CSP_MODE::MODE_BGRA if !premultiplied => PixelLayout::Bgra,
+        libwebp_sys::WEBP_CSP_MODE::MODE_RGBA if !premultiplied => PixelLayout::Rgba,
+        _ => {
+            return Err(at!(Error::InvalidInput(
+                "premultiplied frames cannot be re-encoded".into()
+            )))
+        }
+    };
+
+    let starts: Vec<i32> = decoder.frames.iter().map(|frame| frame.start_ms).collect();
+    let total_ms = decoder.frames.last().map_or(0, |frame| frame.end_ms());
+    let keep = options.select(&starts, total_ms)?;
+
+    let mut encoder: Option<AnimationEncoder> = None;
+    for index in keep {
+        let frame = decoder.seek_to_frame(index as u32)?;
+        let encoder = match encoder.as_mut() {
+            Some(encoder) => encoder,
+            None => {
+                let info = decoder.info();
+                let encoder_options = AnimationEncoderOptions::new()
+                    .loop_count(info.loop_count)
+                    .bgcolor(info.bgcolor);
+                let mut new_encoder =
+                    AnimationEncoder::from_options(frame.width, frame.height, &encoder_options)?;
+                new_encoder.set_config(config.clone())?;
+                #[cfg(feature = "icc")]
+                {
+                    new_encoder.icc_profile = decoder.icc_profile().map(<[u8]>::to_vec);
+                    new_encoder.exif = decoder.exif().map(<[u8]>::to_vec);
+                    new_encoder.xmp = decoder.xmp().map(<[u8]>::to_vec);
+                }
+                encoder.insert(new_encoder)
+            }
+        };
+        encoder.add_frame_internal(encoder.packed(&frame.data, layout), starts[index], None)?;
     }
+
+    encoder
+        .ok_or_else(|| at!(Error::InvalidWebP))?
+        .finish(total_ms)
 }
 
-impl Drop for AnimationDecoder {
-    fn drop(&mut self) {
-        if !self.decoder.is_null() {
-            unsafe {
-                libwebp_sys::WebPAnimDecoderDelete(self.decoder);
+/// Encode a decoded RGBA frame as a still WebP.
+pub(crate) fn encode_canvas(frame: &Frame, config: &EncoderConfig) -> Result<Vec<u8>> {
+    let webp_config = config.to_libwebp()?;
+
+    let mut picture = libwebp_sys::WebPPicture::new()
+        .map_err(|_| at!(Error::InvalidConfig("failed to init picture".into())))?;
+    picture.width = frame.width as i32;
+    picture.height = frame.height as i32;
+    picture.use_argb = 1;
+
+    let stride = (frame.width * 4) as i32;
+    let import_ok =
+        unsafe { libwebp_sys::WebPPictureImportRGBA(&mut picture, frame.data.as_ptr(), stride) };
+    if import_ok == 0 {
+        unsafe { libwebp_sys::WebPPictureFree(&mut picture) };
+        return Err(at!(Error::OutOfMemory));
+    }
+
+    let mut writer = core::mem::MaybeUninit::<libwebp_sys::WebPMemoryWriter>::uninit();
+    unsafe { libwebp_sys::WebPMemoryWriterInit(writer.as_mut_ptr()) };
+    let mut writer = unsafe { writer.assume_init() };
+    picture.writer = Some(libwebp_sys::WebPMemoryWrite);
+    picture.custom_ptr = &mut writer as *mut _ as *mut _;
+
+    let ok = unsafe { libwebp_sys::WebPEncode(&webp_config, &mut picture) };
+    let result = if ok == 0 {
+        Err(at!(Error::EncodeFailed(EncodingError::from(
+            picture.error_code as i32
+        ))))
+    } else {
+        Ok(unsafe { core::slice::from_raw_parts(writer.mem, writer.size) }.to_vec())
+    };
+
+    unsafe {
+        libwebp_sys::WebPPictureFree(&mut picture);
+        libwebp_sys::WebPMemoryWriterClear(&mut writer);
+    }
+    result
+}
+
+/// Borrowed pixels of one [`AnimationEncoder`] frame.
+enum FrameInput<'a> {
+    /// Interleaved 8-bit pixels with stride in bytes.
+    Pixels {
+        data: &'a [u8],
+        stride_bytes: usize,
+        layout: PixelLayout,
+    },
+    /// Native ARGB with stride in pixels.
+    Argb {
+        data: &'a [u32],
+        stride_pixels: usize,
+    },
+    /// YUV 4:2:0 planes.
+    Yuv(YuvPlanesRef<'a>),
+}
+
+impl FrameInput<'_> {
+    /// Validate the buffer against the picture size and hand it to `picture`.
+    ///
+    /// Pixels and ARGB are copied into memory the picture owns, since
+    /// libwebp may modify an ARGB picture while encoding it. YUV planes
+    /// are only read, so they are referenced without copying. On error the
+    /// picture holds no allocations.
+    fn import(&self, picture: &mut libwebp_sys::WebPPicture) -> Result<()> {
+        let (width, height) = (picture.width as usize, picture.height as usize);
+        match *self {
+            FrameInput::Pixels {
+                data,
+                stride_bytes,
+                layout,
+            } => {
+                check_plane(
+                    data.len(),
+                    width * layout.bytes_per_pixel(),
+                    height,
+                    stride_bytes,
+                )?;
+                picture.use_argb = 1;
+                let stride = stride_bytes as i32;
+                let ok = unsafe {
+                    match layout {
+                        PixelLayout::Rgba => {
+                            libwebp_sys::WebPPictureImportRGBA(picture, data.as_ptr(), stride)
+                        }
+                        PixelLayout::Rgb => {
+                            libwebp_sys::WebPPictureImportRGB(picture, data.as_ptr(), stride)
+                        }
+                        PixelLayout::Bgra => {
+                            libwebp_sys::WebPPictureImportBGRA(picture, data.as_ptr(), stride)
+                        }
+                        PixelLayout::Bgr => {
+                            libwebp_sys::WebPPictureImportBGR(picture, data.as_ptr(), stride)
+                        }
+                    }
+                };
+                if ok == 0 {
+                    unsafe { libwebp_sys::WebPPictureFree(picture) };
+                    return Err(at!(Error::OutOfMemory));
+                }
             }
+            FrameInput::Argb {
+                data,
+                stride_pixels,
+            } => {
+                check_plane(data.len(), width, height, stride_pixels)?;
+                picture.use_argb = 1;
+                if unsafe { libwebp_sys::WebPPictureAlloc(picture) } == 0 {
+                    return Err(at!(Error::OutOfMemory));
+                }
+                let dst_stride = picture.argb_stride as usize;
+                // SAFETY: WebPPictureAlloc allocated `height` rows of `argb_stride` pixels
+                let dst =
+                    unsafe { core::slice::from_raw_parts_mut(picture.argb, dst_stride * height) };
+                for (dst, src) in dst
+                    .chunks_exact_mut(dst_stride)
+                    .zip(data.chunks(stride_pixels))
+                {
+                    dst[..width].copy_from_slice(&src[..width]);
+                }
+            }
+            FrameInput::Yuv(planes) => {
+                if planes.u_stride != planes.v_stride {
+                    return Err(at!(Error::InvalidInput(
+                        "U and V planes must have the same stride".into(),
+                    )));
+                }
+                let (uv_width, uv_height) = (width.div_ceil(2), height.div_ceil(2));
+                check_plane(planes.y.len(), width, height, planes.y_stride)?;
+                check_plane(planes.u.len(), uv_width, uv_height, planes.u_stride)?;
+                check_plane(planes.v.len(), uv_width, uv_height, planes.v_stride)?;
+                if let Some(a) = planes.a {
+                    check_plane(a.len(), width, height, planes.a_stride)?;
+                }
+
+                picture.use_argb = 0;
+                picture.colorspace = if planes.a.is_some() {
+                    libwebp_sys::WebPEncCSP::WEBP_YUV420A
+                } else {
+                    libwebp_sys::WebPEncCSP::WEBP_YUV420
+                };
+                picture.y = planes.y.as_ptr() as *mut _;
+                picture.u = planes.u.as_ptr() as *mut _;
+                picture.v = planes.v.as_ptr() as *mut _;
+                picture.y_stride = planes.y_stride as i32;
+                picture.uv_stride = planes.u_stride as i32;
+                if let Some(a) = planes.a {
+                    picture.a = a.as_ptr() as *mut _;
+                    picture.a_stride = planes.a_stride as i32;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that a plane of `rows` rows of `row_len` elements fits in `len`.
+///
+/// The last row only needs `row_len` elements, so sub-views of a larger
+/// buffer are accepted.
+fn check_plane(len: usize, row_len: usize, rows: usize, stride: usize) -> Result<()> {
+    if stride < row_len {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "stride too small: got {}, minimum {}",
+            stride,
+            row_len
+        ))));
+    }
+    let expected = stride
+        .saturating_mul(rows.saturating_sub(1))
+        .saturating_add(row_len);
+    if len < expected {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "buffer too small: got {}, expected {}",
+            len,
+            expected
+        ))));
+    }
+    Ok(())
+}
+
+/// Options for [`AnimationEncoder`].
+///
+/// Mirrors libwebp's `WebPAnimEncoderOptions`. The defaults match
+/// [`AnimationEncoder::new`]: infinite looping, mixed lossy/lossless frames
+/// allowed, and no forced keyframes.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::{AnimationEncoder, AnimationEncoderOptions};
+///
+/// let options = AnimationEncoderOptions::new()
+///     .loop_count(3)
+///     .kmin(5)
+///     .kmax(10)
+///     .bgcolor(0x00000000);
+///
+/// let mut encoder = AnimationEncoder::from_options(320, 240, &options)?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AnimationEncoderOptions {
+    pub(crate) loop_count: u32,
+    pub(crate) bgcolor: u32,
+    pub(crate) minimize_size: bool,
+    pub(crate) kmin: i32,
+    pub(crate) kmax: i32,
+    pub(crate) allow_mixed: bool,
+    pub(crate) verbose: bool,
+}
+
+impl Default for AnimationEncoderOptions {
+    fn default() -> Self {
+        Self {
+            loop_count: 0,
+            bgcolor: 0xffff_ffff,
+            minimize_size: false,
+            kmin: i32::MAX - 1,
+            kmax: i32::MAX,
+            allow_mixed: true,
+            verbose: false,
         }
     }
 }
 
+impl AnimationEncoderOptions {
+    /// Create options with default settings.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the animation loop count (0 = infinite, default).
+    #[must_use]
+    pub fn loop_count(mut self, count: u32) -> Self {
+        self.loop_count = count;
+        self
+    }
+
+    /// Set the background color hint (ARGB, default opaque white).
+    ///
+    /// Players may use this color to clear the canvas; libwebp's own
+    /// decoder ignores it and disposes to transparent.
+    #[must_use]
+    pub fn bgcolor(mut self, argb: u32) -> Self {
+        self.bgcolor = argb;
+        self
+    }
+
+    /// Minimize output size at the cost of encoding time.
+    ///
+    /// Tries every blend/dispose combination per frame and disables
+    /// keyframe insertion (`kmin`/`kmax` are ignored).
+    #[must_use]
+    pub fn minimize_size(mut self, enable: bool) -> Self {
+        self.minimize_size = enable;
+        self
+    }
+
+    /// Set the minimum distance between consecutive keyframes.
+    ///
+    /// libwebp adjusts this so that `kmax / 2 < kmin < kmax`.
+    #[must_use]
+    pub fn kmin(mut self, frames: u32) -> Self {
+        self.kmin = frames.min(i32::MAX as u32) as i32;
+        self
+    }
+
+    /// Set the maximum distance between consecutive keyframes.
+    ///
+    /// `0` disables keyframe insertion, `1` makes every frame a keyframe.
+    /// By default only the first frame is a keyframe. Smaller values make
+    /// seeking cheaper but files larger.
+    #[must_use]
+    pub fn kmax(mut self, frames: u32) -> Self {
+        self.kmax = frames.min(i32::MAX as u32) as i32;
+        self
+    }
+
+    /// Allow mixing lossy and lossless frames (default: true).
+    ///
+    /// When enabled, each frame is encoded both ways and the smaller
+    /// result is kept.
+    #[must_use]
+    pub fn allow_mixed(mut self, enable: bool) -> Self {
+        self.allow_mixed = enable;
+        self
+    }
+
+    /// Print libwebp warnings and debug info to stderr.
+    #[must_use]
+    pub fn verbose(mut self, enable: bool) -> Self {
+        self.verbose = enable;
+        self
+    }
+
+    /// Convert to libwebp options.
+    pub(crate) fn to_libwebp(&self) -> Result<libwebp_sys::WebPAnimEncoderOptions> {
+        let mut options = core::mem::MaybeUninit::<libwebp_sys::WebPAnimEncoderOptions>::uninit();
+        let ok = unsafe {
+            libwebp_sys::WebPAnimEncoderOptionsInitInternal(
+                options.as_mut_ptr(),
+                libwebp_sys::WEBP_MUX_ABI_VERSION as i32,
+            )
+        };
+        if ok == 0 {
+            return Err(at!(Error::InvalidConfig(
+                "failed to init encoder options".into(),
+            )));
+        }
+        let mut options = unsafe { options.assume_init() };
+
+        options.anim_params.loop_count = self.loop_count.min(65535) as i32;
+        options.anim_params.bgcolor = self.bgcolor;
+        options.minimize_size = self.minimize_size as i32;
+        options.kmin = self.kmin;
+        options.kmax = self.kmax;
+        options.allow_mixed = self.allow_mixed as i32;
+        options.verbose = self.verbose as i32;
+
+        Ok(options)
+    }
+}
+
 /// Animated WebP encoder.
 ///
 /// # Example
@@ -263,9 +1437,353 @@ pub struct AnimationEncoder {
     encoder: *mut libwebp_sys::WebPAnimEncoder,
     width: u32,
     height: u32,
-    config: EncoderConfig,
-    #[cfg(feature = "icc")]
-    icc_profile: Option<Vec<u8>>,
+    #[cfg(feature = "std")]
+    options: AnimationEncoderOptions,
+    config: EncoderConfig,
+    #[cfg(feature = "icc")]
+    icc_profile: Option<Vec<u8>>,
+    #[cfg(feature = "icc")]
+    exif: Option<Vec<u8>>,
+    #[cfg(feature = "icc")]
+    xmp: Option<Vec<u8>>,
+    stop: Option<Arc<dyn Stop>>,
+    progress: Option<Box<ProgressCallback>>,
+    frames_done: u32,
+    /// Source frames kept for PSNR, as (time since the first frame, ARGB).
+    psnr_sources: Option<Vec<(i32, Vec<u32>)>>,
+    first_timestamp_ms: Option<i32>,
+    /// Start of the last frame added.
+    last_start_ms: Option<i32>,
+    /// End of the last frame added, if it was added with a duration.
+    last_end_ms: Option<i32>,
+    #[cfg(feature = "std")]
+    parallel: Option<Parallel>,
+}
+
+type ProgressCallback = dyn FnMut(AnimationProgress) + Send;
+
+/// Progress of an [`AnimationEncoder`], passed to its
+/// [progress callback](AnimationEncoder::set_progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AnimationProgress {
+    /// Number of frames fully encoded.
+    pub frames_done: u32,
+    /// Progress of the frame being encoded, 0 to 100.
+    pub frame_percent: u8,
+}
+
+/// Statistics for one frame of an encoded animation.
+///
+/// Returned by [`AnimationEncoder::finish_with_stats`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AnimationFrameStats {
+    /// Start time in milliseconds from animation start. The frame's end
+    /// time, which [`Frame::timestamp_ms`] reports, is `start_ms + duration_ms`.
+    pub start_ms: i32,
+    /// Frame duration in milliseconds.
+    pub duration_ms: u32,
+    /// Size of the frame's `ALPH` and `VP8 `/`VP8L` chunks in bytes.
+    pub coded_size: u32,
+    /// Whether the frame can be decoded without earlier frames in the output.
+    ///
+    /// This is derived from the stored frame with libwebp's decoder rule,
+    /// not reported by the encoder: a frame libwebp encoded as a sub-frame
+    /// also counts if it happens to qualify, for example by following a
+    /// frame disposed to the background. It is not a record of where the
+    /// encoder placed keyframes under [`kmin`](AnimationEncoderOptions::kmin)
+    /// and [`kmax`](AnimationEncoderOptions::kmax).
+    pub keyframe: bool,
+    /// Whether the frame was stored lossy or lossless.
+    pub format: BitstreamFormat,
+    /// Horizontal offset of the emitted sub-rectangle.
+    pub x_offset: u32,
+    /// Vertical offset of the emitted sub-rectangle.
+    pub y_offset: u32,
+    /// Width of the emitted sub-rectangle.
+    pub width: u32,
+    /// Height of the emitted sub-rectangle.
+    pub height: u32,
+    /// Blend method against the previous canvas.
+    pub blend: BlendMethod,
+    /// Dispose method applied after the frame is displayed.
+    pub dispose: DisposeMethod,
+    /// PSNR in dB of the displayed canvas against the source frame, over
+    /// all four ARGB channels (99 for identical pixels).
+    ///
+    /// Color under fully transparent pixels is ignored. `None` unless
+    /// [`AnimationEncoder::set_compute_psnr`] was enabled.
+    pub psnr: Option<f32>,
+}
+
+/// Collect per-frame statistics from an encoded animation.
+///
+/// `sources` holds the ARGB source frames by start time; each stored frame
+/// is compared with the latest source starting at or before it.
+fn frame_stats(
+    webp: &[u8],
+    sources: Option<&[(i32, Vec<u32>)]>,
+    width: u32,
+    height: u32,
+) -> Result<Vec<AnimationFrameStats>> {
+    let formats: Vec<BitstreamFormat> = FrameIter::new(webp)?.map(|raw| raw.format).collect();
+    let frames = scan_frames(FrameIter::new(webp)?, webp);
+
+    let mut decoder = match sources {
+        Some(_) => Some(AnimationDecoder::with_options(
+            webp,
+            ColorMode::Bgra,
+            false,
+        )?),
+        None => None,
+    };
+    let mut canvas = Vec::new();
+
+    let mut stats = Vec::with_capacity(frames.len());
+    for (meta, format) in frames.iter().zip(formats) {
+        let mut psnr = None;
+        if let (Some(decoder), Some(sources)) = (decoder.as_mut(), sources) {
+            let frame = decoder
+                .next_frame_ref()?
+                .ok_or_else(|| at!(Error::InvalidWebP))?;
+            // BGRA bytes are ARGB words in little-endian order
+            canvas.clear();
+            canvas.extend(
+                frame
+                    .data
+                    .chunks_exact(4)
+                    .map(|px| visible(u32::from_le_bytes([px[0], px[1], px[2], px[3]]))),
+            );
+            let source = sources
+                .iter()
+                .rev()
+                .find(|(start, _)| *start <= meta.start_ms)
+                .or(sources.first());
+            if let Some((_, source)) = source {
+                psnr = Some(argb_psnr(&canvas, source, width, height)?);
+            }
+        }
+        stats.push(AnimationFrameStats {
+            start_ms: meta.start_ms,
+            duration_ms: meta.duration_ms,
+            coded_size: meta.bitstream.len() as u32,
+            keyframe: meta.keyframe,
+            format,
+            x_offset: meta.x_offset,
+            y_offset: meta.y_offset,
+            width: meta.width,
+            height: meta.height,
+            blend: meta.blend,
+            dispose: meta.dispose,
+            psnr,
+        });
+    }
+    Ok(stats)
+}
+
+/// Clear the color of a fully transparent ARGB pixel.
+fn visible(argb: u32) -> u32 {
+    if argb >> 24 == 0 {
+        0
+    } else {
+        argb
+    }
+}
+
+/// Copy the pixels of an ARGB picture.
+fn picture_argb(picture: &libwebp_sys::WebPPicture) -> Vec<u32> {
+    let (width, height) = (picture.width as usize, picture.height as usize);
+    let stride = picture.argb_stride as usize;
+    // SAFETY: an ARGB picture holds `height` rows of `argb_stride` pixels
+    let argb = unsafe { core::slice::from_raw_parts(picture.argb, stride * (height - 1) + width) };
+    let mut out = Vec::with_capacity(width * height);
+    for row in argb.chunks(stride).take(height) {
+        out.extend_from_slice(&row[..width]);
+    }
+    out
+}
+
+/// Scale an ARGB image with libwebp's rescaler.
+fn rescale_argb(
+    mut argb: Vec<u32>,
+    width: u32,
+    height: u32,
+    scaled_width: u32,
+    scaled_height: u32,
+) -> Result<Vec<u32>> {
+    let mut picture = libwebp_sys::WebPPicture::new()
+        .map_err(|_| at!(Error::InvalidConfig("failed to init picture".into())))?;
+    picture.use_argb = 1;
+    picture.width = width as i32;
+    picture.height = height as i32;
+    picture.argb = argb.as_mut_ptr();
+    picture.argb_stride = width as i32;
+
+    // On success the picture owns a new, scaled buffer instead of viewing `argb`
+    let ok = unsafe {
+        libwebp_sys::WebPPictureRescale(&mut picture, scaled_width as i32, scaled_height as i32)
+    };
+    if ok == 0 {
+        return Err(at!(Error::OutOfMemory));
+    }
+    let scaled = picture_argb(&picture);
+    unsafe { libwebp_sys::WebPPictureFree(&mut picture) };
+    Ok(scaled)
+}
+
+/// A frame buffered for parallel encoding: timestamp, ARGB pixels and config.
+#[cfg(feature = "std")]
+type BufferedFrame = (i32, Vec<u32>, EncoderConfig);
+
+/// State of an [`AnimationEncoder`] in parallel mode.
+#[cfg(feature = "std")]
+struct Parallel {
+    threads: usize,
+    group_size: usize,
+    /// Frames of the group being collected.
+    pending: Vec<BufferedFrame>,
+    /// Groups sent off for encoding, in order.
+    groups: Vec<Group>,
+}
+
+/// A group of frames encoded by its own encoder.
+#[cfg(feature = "std")]
+struct Group {
+    start_ms: i32,
+    end_ms: i32,
+    frames: u32,
+    /// Config of the first frame, for re-encoding it when joining.
+    config: EncoderConfig,
+    /// The encoding thread, until it has been joined.
+    thread: Option<std::thread::JoinHandle<Result<Vec<u8>>>>,
+    output: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl Parallel {
+    /// Number of groups still encoding.
+    fn running(&self) -> usize {
+        self.groups.iter().filter(|g| g.thread.is_some()).count()
+    }
+
+    /// Wait for the oldest group still encoding and return its frame count.
+    fn join_oldest(&mut self) -> Result<u32> {
+        let Some(group) = self.groups.iter_mut().find(|g| g.thread.is_some()) else {
+            return Ok(0);
+        };
+        if let Some(thread) = group.thread.take() {
+            group.output = thread
+                .join()
+                .map_err(|_| at!(Error::AnimationError("encoder thread panicked".into())))??;
+        }
+        Ok(group.frames)
+    }
+}
+
+/// Encode one group of buffered frames as a standalone animation.
+#[cfg(feature = "std")]
+fn encode_group(
+    width: u32,
+    height: u32,
+    options: &AnimationEncoderOptions,
+    stop: Option<Arc<dyn Stop>>,
+    frames: &[BufferedFrame],
+    end_ms: i32,
+) -> Result<Vec<u8>> {
+    let mut encoder = AnimationEncoder::from_options(width, height, options)?;
+    encoder.stop = stop;
+    for (timestamp_ms, argb, config) in frames {
+        let input = FrameInput::Argb {
+            data: argb,
+            stride_pixels: width as usize,
+        };
+        encoder.add_frame_internal(input, *timestamp_ms, Some(config))?;
+    }
+    let webp = encoder.finish(end_ms)?;
+
+    // libwebp trims the first frame to its visible area, which joining
+    // could only undo by re-encoding the decoded frame. Encode the full
+    // canvas from the source instead, so it is only compressed once.
+    let Some((_, argb, config)) = frames.first() else {
+        return Ok(webp);
+    };
+    let trimmed = FrameIter::new(&webp)?
+        .next()
+        .is_some_and(|first| !first.is_full_canvas(width, height));
+    if !trimmed {
+        return Ok(webp);
+    }
+    let canvas = Frame {
+        data: argb
+            .iter()
+            .flat_map(|&px| {
+                let [b, g, r, a] = px.to_le_bytes();
+                [r, g, b, a]
+            })
+            .collect(),
+        width,
+        height,
+        timestamp_ms: 0,
+        duration_ms: 0,
+    };
+    crate::mux::replace_first_frame(&webp, encode_canvas(&canvas, config)?)
+}
+
+/// PSNR over all channels of two ARGB images, via `WebPPictureDistortion`.
+fn argb_psnr(a: &[u32], b: &[u32], width: u32, height: u32) -> Result<f32> {
+    let view = |argb: &[u32]| -> Result<libwebp_sys::WebPPicture> {
+        let mut picture = libwebp_sys::WebPPicture::new()
+            .map_err(|_| at!(Error::InvalidConfig("failed to init picture".into())))?;
+        picture.use_argb = 1;
+        picture.width = width as i32;
+        picture.height = height as i32;
+        picture.argb = argb.as_ptr() as *mut u32;
+        picture.argb_stride = width as i32;
+        Ok(picture)
+    };
+    let (pa, pb) = (view(a)?, view(b)?);
+    let mut results = [0f32; 5];
+    // SAFETY: both pictures are views of width * height pixels and own no memory
+    let ok = unsafe { libwebp_sys::WebPPictureDistortion(&pa, &pb, 0, results.as_mut_ptr()) };
+    if ok == 0 {
+        return Err(at!(Error::OutOfMemory));
+    }
+    Ok(results[4])
+}
+
+/// State shared with [`anim_progress_hook`] while a frame is encoded.
+struct HookContext<'a> {
+    stop: &'a dyn Stop,
+    progress: Option<&'a mut ProgressCallback>,
+    frames_done: u32,
+    percent: u8,
+}
+
+/// Progress hook for frame encodes: reports progress and checks for a stop.
+///
+/// libwebp may encode several candidates per frame, each counting from 0 to
+/// 100, so only new highs are reported. Returns 1 to continue, 0 to abort.
+extern "C" fn anim_progress_hook(
+    percent: core::ffi::c_int,
+    picture: *const libwebp_sys::WebPPicture,
+) -> core::ffi::c_int {
+    // SAFETY: user_data points to the HookContext of the running add_frame call
+    let ctx = unsafe { &mut *((*picture).user_data as *mut HookContext<'_>) };
+    if ctx.stop.should_stop() {
+        return 0;
+    }
+    let percent = percent.clamp(0, 100) as u8;
+    if percent > ctx.percent {
+        ctx.percent = percent;
+        if let Some(progress) = ctx.progress.as_mut() {
+            progress(AnimationProgress {
+                frames_done: ctx.frames_done,
+                frame_percent: percent,
+            });
+        }
+    }
+    1
 }
 
 // SAFETY: WebPAnimEncoder is thread-safe for single-threaded access
@@ -290,33 +1808,36 @@ impl AnimationEncoder {
         height: u32,
         allow_mixed: bool,
         loop_count: u32,
+    ) -> Result<Self> {
+        let options = AnimationEncoderOptions::new()
+            .allow_mixed(allow_mixed)
+            .loop_count(loop_count);
+        Self::from_options(width, height, &options)
+    }
+
+    /// Create a new animation encoder from [`AnimationEncoderOptions`].
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Canvas width
+    /// * `height` - Canvas height
+    /// * `options` - Keyframe, loop, background and size options
+    pub fn from_options(
+        width: u32,
+        height: u32,
+        options: &AnimationEncoderOptions,
     ) -> Result<Self> {
         if width == 0 || height == 0 || width > 16383 || height > 16383 {
             return Err(at!(Error::InvalidInput("invalid dimensions".into())));
         }
 
-        let mut options = core::mem::MaybeUninit::<libwebp_sys::WebPAnimEncoderOptions>::uninit();
-        let ok = unsafe {
-            libwebp_sys::WebPAnimEncoderOptionsInitInternal(
-                options.as_mut_ptr(),
-                libwebp_sys::WEBP_MUX_ABI_VERSION as i32,
-            )
-        };
-        if ok == 0 {
-            return Err(at!(Error::InvalidConfig(
-                "failed to init encoder options".into(),
-            )));
-        }
-        let mut options = unsafe { options.assume_init() };
-
-        options.allow_mixed = allow_mixed as i32;
-        options.anim_params.loop_count = loop_count as i32;
+        let webp_options = options.to_libwebp()?;
 
         let encoder = unsafe {
             libwebp_sys::WebPAnimEncoderNewInternal(
                 width as i32,
                 height as i32,
-                &options,
+                &webp_options,
                 libwebp_sys::WEBP_MUX_ABI_VERSION as i32,
             )
         };
@@ -329,12 +1850,67 @@ impl AnimationEncoder {
             encoder,
             width,
             height,
+            #[cfg(feature = "std")]
+            options: options.clone(),
             config: EncoderConfig::default(),
             #[cfg(feature = "icc")]
             icc_profile: None,
+            #[cfg(feature = "icc")]
+            exif: None,
+            #[cfg(feature = "icc")]
+            xmp: None,
+            stop: None,
+            progress: None,
+            frames_done: 0,
+            psnr_sources: None,
+            first_timestamp_ms: None,
+            last_start_ms: None,
+            last_end_ms: None,
+            #[cfg(feature = "std")]
+            parallel: None,
         })
     }
 
+    /// Create a new animation encoder that encodes frames with `config`.
+    ///
+    /// Every [`EncoderConfig`] option (method, near-lossless, alpha, exact,
+    /// sharp YUV, target size, ...) applies to each frame. The configuration
+    /// is validated up front, as [`EncoderConfig::validate`] does for stills.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use webpx::{AnimationEncoder, EncoderConfig};
+    ///
+    /// let config = EncoderConfig::new()
+    ///     .quality(80.0)
+    ///     .method(6)
+    ///     .sharp_yuv(true)
+    ///     .alpha_quality(90);
+    /// let mut encoder = AnimationEncoder::with_config(320, 240, config)?;
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn with_config(width: u32, height: u32, config: EncoderConfig) -> Result<Self> {
+        let mut encoder = Self::new(width, height)?;
+        encoder.set_config(config)?;
+        Ok(encoder)
+    }
+
+    /// Replace the encoder configuration used for subsequent frames.
+    ///
+    /// Returns an error and keeps the current configuration if `config`
+    /// fails validation.
+    pub fn set_config(&mut self, config: EncoderConfig) -> Result<()> {
+        config.validate()?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Get the encoder configuration used for frames.
+    pub fn config(&self) -> &EncoderConfig {
+        &self.config
+    }
+
     /// Set encoding quality.
     pub fn set_quality(&mut self, quality: f32) {
         self.config.quality = quality;
@@ -356,6 +1932,219 @@ impl AnimationEncoder {
         self.icc_profile = Some(profile);
     }
 
+    /// Set EXIF metadata to embed.
+    #[cfg(feature = "icc")]
+    pub fn set_exif(&mut self, exif: Vec<u8>) {
+        self.exif = Some(exif);
+    }
+
+    /// Set XMP metadata to embed.
+    #[cfg(feature = "icc")]
+    pub fn set_xmp(&mut self, xmp: Vec<u8>) {
+        self.xmp = Some(xmp);
+    }
+
+    /// Set a cooperative cancellation token.
+    ///
+    /// `stop` is checked before each frame, during frame encoding and at the
+    /// start of [`finish`](Self::finish). Once an operation has failed with
+    /// [`Error::Stopped`], the encoder should be dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    /// use webpx::{AnimationEncoder, Stop, StopReason};
+    ///
+    /// struct Disconnected(Arc<AtomicBool>);
+    ///
+    /// impl Stop for Disconnected {
+    ///     fn check(&self) -> Result<(), StopReason> {
+    ///         if self.0.load(Ordering::Relaxed) {
+    ///             Err(StopReason::Cancelled)
+    ///         } else {
+    ///             Ok(())
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let disconnected = Arc::new(AtomicBool::new(false));
+    /// let mut encoder = AnimationEncoder::new(320, 240)?;
+    /// encoder.set_stop(Disconnected(Arc::clone(&disconnected)));
+    /// encoder.set_progress(|p| println!("{} frames, {}%", p.frames_done, p.frame_percent));
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn set_stop(&mut self, stop: impl Stop + 'static) {
+        self.stop = Some(Arc::new(stop));
+    }
+
+    /// Set a callback reporting encoding progress.
+    ///
+    /// The callback runs inside libwebp while a frame is encoded, each time
+    /// the frame's progress advances, and once after every frame with
+    /// `frame_percent` reset to 0. It must not panic.
+    pub fn set_progress(&mut self, callback: impl FnMut(AnimationProgress) + Send + 'static) {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// Compute the PSNR of each frame for [`finish_with_stats`](Self::finish_with_stats).
+    ///
+    /// Must be called before the first frame is added. This keeps an ARGB
+    /// copy of every frame until the animation is finished.
+    pub fn set_compute_psnr(&mut self, enable: bool) -> Result<()> {
+        if self.last_start_ms.is_some() {
+            return Err(at!(Error::InvalidInput(
+                "PSNR must be enabled before the first frame".into()
+            )));
+        }
+        self.psnr_sources = enable.then(Vec::new);
+        Ok(())
+    }
+
+    /// Encode groups of frames on up to `threads` threads, capped at the
+    /// available parallelism.
+    ///
+    /// Frames are split into groups of `group_size`. Each group is encoded by
+    /// its own libwebp encoder, starting with a keyframe, and the groups are
+    /// joined with the mux API when the animation is finished. Frame
+    /// differences are only optimized within a group, so the output is
+    /// usually somewhat larger than a sequential encode; larger groups cost
+    /// less size but give less parallelism.
+    ///
+    /// Added frames are copied and buffered until their group is encoded.
+    /// The [progress callback](Self::set_progress) is called as groups
+    /// complete, with `frame_percent` always 0.
+    ///
+    /// Must be called before the first frame is added.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use webpx::AnimationEncoder;
+    ///
+    /// let frames: Vec<Vec<u8>> = vec![vec![0u8; 320 * 240 * 4]; 100]; // placeholder
+    /// let mut encoder = AnimationEncoder::new(320, 240)?;
+    /// encoder.set_parallel(4, 25)?;
+    /// for (i, frame) in frames.iter().enumerate() {
+    ///     encoder.add_frame_rgba(frame, i as i32 * 40)?;
+    /// }
+    /// let webp = encoder.finish(4000)?;
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn set_parallel(&mut self, threads: usize, group_size: u32) -> Result<()> {
+        if threads == 0 || group_size == 0 {
+            return Err(at!(Error::InvalidInput(
+                "thread count and group size must be non-zero".into()
+            )));
+        }
+        if self.last_start_ms.is_some() {
+            return Err(at!(Error::InvalidInput(
+                "parallel encoding must be enabled before the first frame".into()
+            )));
+        }
+        let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+        self.parallel = Some(Parallel {
+            threads: threads.min(available),
+            group_size: group_size as usize,
+            pending: Vec::new(),
+            groups: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Return [`Error::Stopped`] if the stop token has been triggered.
+    fn check_stop(&self) -> Result<()> {
+        match &self.stop {
+            Some(stop) => stop.check().map_err(|reason| at!(Error::Stopped(reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// Add a frame with typed pixel data and a duration.
+    ///
+    /// The frame starts where the previous frame ended (at 0 for the first
+    /// frame), so no timestamps need to be tracked. When every frame has a
+    /// duration, [`finish_after_last_frame`](Self::finish_after_last_frame)
+    /// ends the animation without an end timestamp.
+    ///
+    /// Fails if the previous frame was added with a timestamp only, since
+    /// its end is not known yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use rgb::RGBA8;
+    /// use webpx::AnimationEncoder;
+    ///
+    /// let frames = vec![(vec![RGBA8::new(0, 0, 0, 255); 64 * 64], 40u32); 25]; // placeholder
+    /// let mut encoder = AnimationEncoder::new(64, 64)?;
+    /// for (pixels, duration_ms) in &frames {
+    ///     encoder.add_frame_with_duration(pixels, *duration_ms)?;
+    /// }
+    /// let webp = encoder.finish_after_last_frame()?; // ends at 1000ms
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn add_frame_with_duration<P: EncodePixel>(
+        &mut self,
+        pixels: &[P],
+        duration_ms: u32,
+    ) -> Result<()> {
+        let start = match (self.last_start_ms, self.last_end_ms) {
+            (None, _) => 0,
+            (Some(_), Some(end)) => end,
+            (Some(start), None) => {
+                return Err(at!(Error::InvalidInput(alloc::format!(
+                    "previous frame at {}ms was added without a duration",
+                    start
+                ))))
+            }
+        };
+        let end = i32::try_from(duration_ms)
+            .ok()
+            .and_then(|duration| start.checked_add(duration))
+            .ok_or_else(|| {
+                at!(Error::InvalidInput(alloc::format!(
+                    "frame duration {}ms starting at {}ms overflows the timeline",
+                    duration_ms,
+                    start
+                )))
+            })?;
+
+        let bpp = P::LAYOUT.bytes_per_pixel();
+        let data = unsafe {
+            core::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * bpp)
+        };
+        self.add_frame_internal(self.packed(data, P::LAYOUT), start, None)?;
+        self.last_end_ms = Some(end);
+        Ok(())
+    }
+
+    /// Check that a frame starting at `timestamp_ms` keeps the timeline monotonic.
+    fn check_timestamp(&self, timestamp_ms: i32) -> Result<()> {
+        let Some(start) = self.last_start_ms else {
+            return Ok(());
+        };
+        if timestamp_ms < start {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "timestamp {}ms is before the previous frame at {}ms",
+                timestamp_ms,
+                start
+            ))));
+        }
+        if let Some(end) = self.last_end_ms {
+            if timestamp_ms < end {
+                return Err(at!(Error::InvalidInput(alloc::format!(
+                    "timestamp {}ms is before the previous frame ends at {}ms",
+                    timestamp_ms,
+                    end
+                ))));
+            }
+        }
+        Ok(())
+    }
+
     /// Add a frame with typed pixel data.
     ///
     /// This is the preferred method for type-safe frame addition with rgb crate types.
@@ -375,7 +2164,32 @@ impl AnimationEncoder {
         let data = unsafe {
             core::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * bpp)
         };
-        self.add_frame_internal(data, timestamp_ms, P::LAYOUT)
+        self.add_frame_internal(self.packed(data, P::LAYOUT), timestamp_ms, None)
+    }
+
+    /// Add a frame encoded with its own configuration.
+    ///
+    /// `config` overrides the encoder's configuration for this frame only,
+    /// so a screenshot can be lossless and the next photo lossy. With
+    /// `allow_mixed` enabled, libwebp may still pick the other mode if it is
+    /// smaller.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - Frame pixel data
+    /// * `timestamp_ms` - Frame timestamp in milliseconds from animation start
+    /// * `config` - Encoder configuration for this frame
+    pub fn add_frame_with_config<P: EncodePixel>(
+        &mut self,
+        pixels: &[P],
+        timestamp_ms: i32,
+        config: &EncoderConfig,
+    ) -> Result<()> {
+        let bpp = P::LAYOUT.bytes_per_pixel();
+        let data = unsafe {
+            core::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * bpp)
+        };
+        self.add_frame_internal(self.packed(data, P::LAYOUT), timestamp_ms, Some(config))
     }
 
     /// Add a frame with RGBA byte data.
@@ -385,7 +2199,7 @@ impl AnimationEncoder {
     /// * `data` - Frame pixel data (RGBA, 4 bytes per pixel)
     /// * `timestamp_ms` - Frame timestamp in milliseconds from animation start
     pub fn add_frame_rgba(&mut self, data: &[u8], timestamp_ms: i32) -> Result<()> {
-        self.add_frame_internal(data, timestamp_ms, PixelLayout::Rgba)
+        self.add_frame_internal(self.packed(data, PixelLayout::Rgba), timestamp_ms, None)
     }
 
     /// Add a frame with RGB byte data (no alpha).
@@ -395,7 +2209,7 @@ impl AnimationEncoder {
     /// * `data` - Frame pixel data (RGB, 3 bytes per pixel)
     /// * `timestamp_ms` - Frame timestamp in milliseconds from animation start
     pub fn add_frame_rgb(&mut self, data: &[u8], timestamp_ms: i32) -> Result<()> {
-        self.add_frame_internal(data, timestamp_ms, PixelLayout::Rgb)
+        self.add_frame_internal(self.packed(data, PixelLayout::Rgb), timestamp_ms, None)
     }
 
     /// Add a frame with BGRA byte data.
@@ -407,7 +2221,7 @@ impl AnimationEncoder {
     /// * `data` - Frame pixel data (BGRA, 4 bytes per pixel)
     /// * `timestamp_ms` - Frame timestamp in milliseconds from animation start
     pub fn add_frame_bgra(&mut self, data: &[u8], timestamp_ms: i32) -> Result<()> {
-        self.add_frame_internal(data, timestamp_ms, PixelLayout::Bgra)
+        self.add_frame_internal(self.packed(data, PixelLayout::Bgra), timestamp_ms, None)
     }
 
     /// Add a frame with BGR byte data (no alpha).
@@ -419,61 +2233,181 @@ impl AnimationEncoder {
     /// * `data` - Frame pixel data (BGR, 3 bytes per pixel)
     /// * `timestamp_ms` - Frame timestamp in milliseconds from animation start
     pub fn add_frame_bgr(&mut self, data: &[u8], timestamp_ms: i32) -> Result<()> {
-        self.add_frame_internal(data, timestamp_ms, PixelLayout::Bgr)
+        self.add_frame_internal(self.packed(data, PixelLayout::Bgr), timestamp_ms, None)
     }
 
-    /// Internal: Add a frame with a specific pixel layout.
-    fn add_frame_internal(
+    /// Add a frame from an imgref image.
+    ///
+    /// The image must have the canvas dimensions. Its stride is honored, so a
+    /// sub-image of a larger buffer can be passed without repacking.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use rgb::RGBA8;
+    /// use webpx::AnimationEncoder;
+    ///
+    /// // Encode the top-left 100x100 region of a 640x480 frame buffer
+    /// let pixels = vec![RGBA8::new(255, 0, 0, 255); 640 * 480];
+    /// let frame = imgref::Img::new(pixels.as_slice(), 640, 480);
+    ///
+    /// let mut encoder = AnimationEncoder::new(100, 100)?;
+    /// encoder.add_frame_img(frame.sub_image(0, 0, 100, 100), 0)?;
+    /// let webp = encoder.finish(100)?;
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn add_frame_img<P: EncodePixel>(
         &mut self,
-        data: &[u8],
+        img: ImgRef<'_, P>,
+        timestamp_ms: i32,
+    ) -> Result<()> {
+        self.check_frame_size(img.width() as u32, img.height() as u32)?;
+        self.add_frame_stride(img.buf(), img.stride() as u32, timestamp_ms)
+    }
+
+    /// Add a frame of typed pixels with an explicit row stride.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - Frame pixel data
+    /// * `stride_pixels` - Row stride in pixels (must be >= canvas width)
+    /// * `timestamp_ms` - Frame timestamp in milliseconds from animation start
+    pub fn add_frame_stride<P: EncodePixel>(
+        &mut self,
+        pixels: &[P],
+        stride_pixels: u32,
+        timestamp_ms: i32,
+    ) -> Result<()> {
+        let bpp = P::LAYOUT.bytes_per_pixel();
+        let data = unsafe {
+            core::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * bpp)
+        };
+        let input = FrameInput::Pixels {
+            data,
+            stride_bytes: stride_pixels as usize * bpp,
+            layout: P::LAYOUT,
+        };
+        self.add_frame_internal(input, timestamp_ms, None)
+    }
+
+    /// Add a frame of native ARGB pixels.
+    ///
+    /// Each `u32` is a pixel in `0xAARRGGBB` format, as in
+    /// [`Encoder::new_argb`](crate::Encoder::new_argb).
+    pub fn add_frame_argb(&mut self, data: &[u32], timestamp_ms: i32) -> Result<()> {
+        self.add_frame_argb_stride(data, self.width, timestamp_ms)
+    }
+
+    /// Add a frame of native ARGB pixels with an explicit row stride.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - ARGB pixel data as u32 values
+    /// * `stride_pixels` - Row stride in pixels (must be >= canvas width)
+    /// * `timestamp_ms` - Frame timestamp in milliseconds from animation start
+    pub fn add_frame_argb_stride(
+        &mut self,
+        data: &[u32],
+        stride_pixels: u32,
         timestamp_ms: i32,
-        layout: PixelLayout,
     ) -> Result<()> {
-        let bpp = layout.bytes_per_pixel();
-        let expected = (self.width as usize) * (self.height as usize) * bpp;
-        if data.len() < expected {
-            return Err(at!(Error::InvalidInput("buffer too small".into())));
+        let input = FrameInput::Argb {
+            data,
+            stride_pixels: stride_pixels as usize,
+        };
+        self.add_frame_internal(input, timestamp_ms, None)
+    }
+
+    /// Add a frame of YUV 4:2:0 planes.
+    ///
+    /// The planes must have the canvas dimensions, and the U and V planes
+    /// must share a stride. libwebp's animation encoder works on RGB(A)
+    /// canvases, so the planes are converted to ARGB first, which incurs a
+    /// small loss.
+    pub fn add_frame_yuv(&mut self, planes: YuvPlanesRef<'_>, timestamp_ms: i32) -> Result<()> {
+        self.check_frame_size(planes.width, planes.height)?;
+        self.add_frame_internal(FrameInput::Yuv(planes), timestamp_ms, None)
+    }
+
+    /// Check that a frame has the canvas dimensions.
+    fn check_frame_size(&self, width: u32, height: u32) -> Result<()> {
+        if width != self.width || height != self.height {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "frame is {}x{}, canvas is {}x{}",
+                width,
+                height,
+                self.width,
+                self.height
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Describe tightly packed pixels covering the canvas.
+    fn packed<'d>(&self, data: &'d [u8], layout: PixelLayout) -> FrameInput<'d> {
+        FrameInput::Pixels {
+            data,
+            stride_bytes: self.width as usize * layout.bytes_per_pixel(),
+            layout,
         }
+    }
 
-        let webp_config = self.config.to_libwebp()?;
+    /// Internal: Add a frame from any supported input.
+    ///
+    /// `config` overrides the encoder-wide configuration when set.
+    fn add_frame_internal(
+        &mut self,
+        input: FrameInput<'_>,
+        timestamp_ms: i32,
+        config: Option<&EncoderConfig>,
+    ) -> Result<()> {
+        self.check_stop()?;
+        self.check_timestamp(timestamp_ms)?;
+        let webp_config = config.unwrap_or(&self.config).to_libwebp()?;
 
         let mut picture = libwebp_sys::WebPPicture::new()
             .map_err(|_| at!(Error::InvalidConfig("failed to init picture".into())))?;
 
         picture.width = self.width as i32;
         picture.height = self.height as i32;
-        picture.use_argb = 1;
+        input.import(&mut picture)?;
 
-        let stride = (self.width as usize * bpp) as i32;
-        let import_ok = unsafe {
-            match layout {
-                PixelLayout::Rgba => {
-                    libwebp_sys::WebPPictureImportRGBA(&mut picture, data.as_ptr(), stride)
-                }
-                PixelLayout::Rgb => {
-                    libwebp_sys::WebPPictureImportRGB(&mut picture, data.as_ptr(), stride)
-                }
-                PixelLayout::Bgra => {
-                    libwebp_sys::WebPPictureImportBGRA(&mut picture, data.as_ptr(), stride)
-                }
-                PixelLayout::Bgr => {
-                    libwebp_sys::WebPPictureImportBGR(&mut picture, data.as_ptr(), stride)
-                }
+        #[cfg(feature = "std")]
+        if self.parallel.is_some() {
+            let result = self.buffer_frame(&mut picture, timestamp_ms, config);
+            if result.is_ok() {
+                self.record_frame(&picture, timestamp_ms);
             }
-        };
-
-        if import_ok == 0 {
             unsafe { libwebp_sys::WebPPictureFree(&mut picture) };
-            return Err(at!(Error::OutOfMemory));
+            return result;
+        }
+
+        let mut ctx = HookContext {
+            stop: self.stop.as_deref().unwrap_or(&Unstoppable),
+            progress: self.progress.as_deref_mut(),
+            frames_done: self.frames_done,
+            percent: 0,
+        };
+        if self.stop.is_some() || ctx.progress.is_some() {
+            picture.progress_hook = Some(anim_progress_hook);
+            picture.user_data = &mut ctx as *mut _ as *mut _;
         }
 
         let ok = unsafe {
             libwebp_sys::WebPAnimEncoderAdd(self.encoder, &mut picture, timestamp_ms, &webp_config)
         };
 
+        if ok != 0 {
+            // libwebp has converted any YUV input to ARGB by now
+            self.record_frame(&picture, timestamp_ms);
+        }
+
         unsafe { libwebp_sys::WebPPictureFree(&mut picture) };
 
         if ok == 0 {
+            if let Some(stop) = &self.stop {
+                stop.check().map_err(|reason| at!(Error::Stopped(reason)))?;
+            }
             let error_msg = unsafe {
                 let ptr = libwebp_sys::WebPAnimEncoderGetError(self.encoder);
                 if ptr.is_null() {
@@ -487,15 +2421,209 @@ impl AnimationEncoder {
             return Err(at!(Error::AnimationError(error_msg.into())));
         }
 
+        self.frames_completed(1);
+        Ok(())
+    }
+
+    /// Update the timeline after adding the ARGB `picture` at `timestamp_ms`.
+    fn record_frame(&mut self, picture: &libwebp_sys::WebPPicture, timestamp_ms: i32) {
+        self.last_start_ms = Some(timestamp_ms);
+        self.last_end_ms = None;
+        let first = *self.first_timestamp_ms.get_or_insert(timestamp_ms);
+        if let Some(sources) = self.psnr_sources.as_mut() {
+            let mut argb = picture_argb(picture);
+            argb.iter_mut().for_each(|px| *px = visible(*px));
+            sources.push((timestamp_ms.saturating_sub(first), argb));
+        }
+    }
+
+    /// Count `frames` as encoded and report progress.
+    fn frames_completed(&mut self, frames: u32) {
+        self.frames_done += frames;
+        if let Some(progress) = self.progress.as_mut() {
+            progress(AnimationProgress {
+                frames_done: self.frames_done,
+                frame_percent: 0,
+            });
+        }
+    }
+
+    /// Copy a frame into the pending group, first sending off the pending
+    /// group if it is full.
+    #[cfg(feature = "std")]
+    fn buffer_frame(
+        &mut self,
+        picture: &mut libwebp_sys::WebPPicture,
+        timestamp_ms: i32,
+        config: Option<&EncoderConfig>,
+    ) -> Result<()> {
+        if picture.use_argb == 0 && unsafe { libwebp_sys::WebPPictureYUVAToARGB(picture) } == 0 {
+            return Err(at!(Error::OutOfMemory));
+        }
+        let frame = (
+            timestamp_ms,
+            picture_argb(picture),
+            config.unwrap_or(&self.config).clone(),
+        );
+        if let Some(parallel) = self.parallel.as_ref() {
+            if parallel.pending.len() >= parallel.group_size {
+                self.spawn_group(timestamp_ms)?;
+            }
+        }
+        if let Some(parallel) = self.parallel.as_mut() {
+            parallel.pending.push(frame);
+        }
+        Ok(())
+    }
+
+    /// Start encoding the pending group on a new thread, ending it at
+    /// `end_ms`. Waits for the oldest group first if all threads are busy.
+    #[cfg(feature = "std")]
+    fn spawn_group(&mut self, end_ms: i32) -> Result<()> {
+        loop {
+            let Some(parallel) = self.parallel.as_mut() else {
+                return Ok(());
+            };
+            if parallel.running() < parallel.threads {
+                break;
+            }
+            let frames = parallel.join_oldest()?;
+            self.frames_completed(frames);
+        }
+        let Some(parallel) = self.parallel.as_mut() else {
+            return Ok(());
+        };
+        let frames = core::mem::take(&mut parallel.pending);
+        let Some((start_ms, _, config)) = frames.first() else {
+            return Ok(());
+        };
+        let (start_ms, config) = (*start_ms, config.clone());
+        let (width, height) = (self.width, self.height);
+        let options = self.options.clone();
+        let stop = self.stop.clone();
+        let group = Group {
+            start_ms,
+            end_ms,
+            frames: frames.len() as u32,
+            config,
+            thread: Some(std::thread::spawn(move || {
+                encode_group(width, height, &options, stop, &frames, end_ms)
+            })),
+            output: Vec::new(),
+        };
+        parallel.groups.push(group);
         Ok(())
     }
 
+    /// Encode the last group, wait for all groups and join them.
+    #[cfg(feature = "std")]
+    fn finish_parallel(&mut self, end_ms: i32) -> Result<Vec<u8>> {
+        self.spawn_group(end_ms)?;
+        while let Some(parallel) = self.parallel.as_mut() {
+            if parallel.running() == 0 {
+                break;
+            }
+            let frames = parallel.join_oldest()?;
+            self.frames_completed(frames);
+        }
+        let Some(parallel) = self.parallel.take() else {
+            return Err(at!(Error::AnimationError("not in parallel mode".into())));
+        };
+        let mut segments: Vec<(Vec<u8>, u32, EncoderConfig)> = parallel
+            .groups
+            .into_iter()
+            .map(|group| {
+                let span = group.end_ms.saturating_sub(group.start_ms).max(0) as u32;
+                (group.output, span, group.config)
+            })
+            .collect();
+        if segments.len() == 1 {
+            return Ok(segments.remove(0).0);
+        }
+        // Same loop count and background as a sequential encode would write
+        let params = self.options.to_libwebp()?.anim_params;
+        crate::mux::join_segments(&segments, params.bgcolor, params.loop_count as u32)
+    }
+
+    /// Finish encoding and return the WebP data with per-frame statistics.
+    ///
+    /// Statistics describe the frames as stored in the output, which can
+    /// differ from the frames added: libwebp merges a frame identical to the
+    /// previous one into its duration, and splits durations too long for
+    /// the container. PSNR is only computed if
+    /// [`set_compute_psnr`](Self::set_compute_psnr) was enabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use webpx::AnimationEncoder;
+    ///
+    /// let frames: Vec<Vec<u8>> = vec![vec![0u8; 320 * 240 * 4]; 10]; // placeholder
+    /// let mut encoder = AnimationEncoder::new(320, 240)?;
+    /// encoder.set_compute_psnr(true)?;
+    /// for (i, frame) in frames.iter().enumerate() {
+    ///     encoder.add_frame_rgba(frame, i as i32 * 100)?;
+    /// }
+    /// let (webp, stats) = encoder.finish_with_stats(1000)?;
+    /// for (i, frame) in stats.iter().enumerate() {
+    ///     println!(
+    ///         "frame {}: {} bytes, {:?}, keyframe: {}, {}x{} at ({}, {}), PSNR {:?}",
+    ///         i, frame.coded_size, frame.format, frame.keyframe,
+    ///         frame.width, frame.height, frame.x_offset, frame.y_offset, frame.psnr,
+    ///     );
+    /// }
+    /// # Ok::<(), webpx::At<webpx::Error>>(())
+    /// ```
+    pub fn finish_with_stats(
+        mut self,
+        end_timestamp_ms: i32,
+    ) -> Result<(Vec<u8>, Vec<AnimationFrameStats>)> {
+        let (width, height) = (self.width, self.height);
+        let sources = self.psnr_sources.take();
+        let webp = self.finish(end_timestamp_ms)?;
+        let stats = frame_stats(&webp, sources.as_deref(), width, height)?;
+        Ok((webp, stats))
+    }
+
     /// Finish encoding and return the WebP data.
     ///
     /// # Arguments
     ///
     /// * `end_timestamp_ms` - End timestamp (determines duration of last frame)
     pub fn finish(self, end_timestamp_ms: i32) -> Result<Vec<u8>> {
+        self.check_stop()?;
+        self.check_timestamp(end_timestamp_ms)?;
+        self.finish_at(end_timestamp_ms)
+    }
+
+    /// Finish encoding where the last frame ends and return the WebP data.
+    ///
+    /// The last frame must have been added with
+    /// [`add_frame_with_duration`](Self::add_frame_with_duration), so that
+    /// its end is known.
+    pub fn finish_after_last_frame(self) -> Result<Vec<u8>> {
+        self.check_stop()?;
+        let end_timestamp_ms = self.last_end_ms.ok_or_else(|| {
+            at!(Error::InvalidInput(
+                "the last frame was added without a duration".into()
+            ))
+        })?;
+        self.finish_at(end_timestamp_ms)
+    }
+
+    /// Flush the encoder with the last frame ending at `end_timestamp_ms`.
+    fn finish_at(self, end_timestamp_ms: i32) -> Result<Vec<u8>> {
+        #[cfg(feature = "std")]
+        if self
+            .parallel
+            .as_ref()
+            .is_some_and(|p| !p.pending.is_empty())
+        {
+            let mut encoder = self;
+            let result = encoder.finish_parallel(end_timestamp_ms)?;
+            return encoder.embed_metadata(result);
+        }
+
         // Add NULL frame to signal end
         let ok = unsafe {
             libwebp_sys::WebPAnimEncoderAdd(
@@ -532,13 +2660,28 @@ impl AnimationEncoder {
             vec
         };
 
-        // Embed ICC profile if set
+        self.embed_metadata(result)
+    }
+
+    /// Embed the ICC profile and EXIF/XMP metadata, if set, into the
+    /// finished animation.
+    fn embed_metadata(&self, webp: Vec<u8>) -> Result<Vec<u8>> {
         #[cfg(feature = "icc")]
-        if let Some(ref icc) = self.icc_profile {
-            return crate::mux::embed_icc(&result, icc);
-        }
+        let webp = {
+            let mut webp = webp;
+            if let Some(ref icc) = self.icc_profile {
+                webp = crate::mux::embed_icc(&webp, icc)?;
+            }
+            if let Some(ref exif) = self.exif {
+                webp = crate::mux::embed_exif(&webp, exif)?;
+            }
+            if let Some(ref xmp) = self.xmp {
+                webp = crate::mux::embed_xmp(&webp, xmp)?;
+            }
+            webp
+        };
 
-        Ok(result)
+        Ok(webp)
     }
 }
 
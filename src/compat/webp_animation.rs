@@ -348,7 +348,7 @@ impl<'a> Decoder<'a> {
 #[cfg(feature = "animation")]
 impl<'a> IntoIterator for Decoder<'a> {
     type Item = Frame;
-    type IntoIter = DecoderIterator;
+    type IntoIter = DecoderIterator<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
         let inner = crate::AnimationDecoder::with_options(
@@ -367,13 +367,13 @@ impl<'a> IntoIterator for Decoder<'a> {
 
 /// Iterator over animation frames.
 #[cfg(feature = "animation")]
-pub struct DecoderIterator {
-    inner: Option<crate::AnimationDecoder>,
+pub struct DecoderIterator<'a> {
+    inner: Option<crate::AnimationDecoder<'a>>,
     color_mode: ColorMode,
 }
 
 #[cfg(feature = "animation")]
-impl Iterator for DecoderIterator {
+impl Iterator for DecoderIterator<'_> {
     type Item = Frame;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -0,0 +1,506 @@
+//! Animation frame compositing.
+//!
+//! A port of libwebp's `anim_decode.c` canvas logic on top of the demux API.
+//! Owning the canvas lets [`AnimationDecoder`](crate::AnimationDecoder)
+//! restart decoding at any keyframe instead of only at frame 0.
+
+use crate::demux::{BlendMethod, DisposeMethod, FrameIter};
+use crate::error::{DecodingError, Error, Result};
+use alloc::vec::Vec;
+use core::ops::Range;
+use whereat::*;
+
+/// Frame header information, with the bitstream as a range into the input.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameMeta {
+    pub(crate) x_offset: u32,
+    pub(crate) y_offset: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) duration_ms: u32,
+    pub(crate) blend: BlendMethod,
+    pub(crate) dispose: DisposeMethod,
+    pub(crate) has_alpha: bool,
+    /// Whether the frame can be rendered without any earlier frame.
+    pub(crate) keyframe: bool,
+    /// Start time in milliseconds.
+    pub(crate) start_ms: i32,
+    pub(crate) bitstream: Range<usize>,
+}
+
+impl FrameMeta {
+    pub(crate) fn is_full_canvas(&self, canvas_width: u32, canvas_height: u32) -> bool {
+        self.x_offset == 0
+            && self.y_offset == 0
+            && self.width == canvas_width
+            && self.height == canvas_height
+    }
+
+    /// End time in milliseconds (the timestamp libwebp reports for the frame).
+    pub(crate) fn end_ms(&self) -> i32 {
+        self.start_ms.saturating_add(self.duration_ms as i32)
+    }
+}
+
+/// Read the header of every frame and classify keyframes.
+///
+/// The keyframe rule matches libwebp's `IsKeyFrame`: the first frame, a
+/// full-canvas frame that does not blend, or any frame following one whose
+/// disposal cleared the whole visible canvas.
+pub(crate) fn scan_frames(iter: FrameIter<'_>, data: &[u8]) -> Vec<FrameMeta> {
+    let canvas_width = iter.canvas_width();
+    let canvas_height = iter.canvas_height();
+    let base = data.as_ptr() as usize;
+
+    let mut frames: Vec<FrameMeta> = Vec::with_capacity(iter.len());
+    let mut start_ms = 0i32;
+    for raw in iter {
+        let offset = (raw.bitstream.as_ptr() as usize).saturating_sub(base);
+        let keyframe = match frames.last() {
+            None => true,
+            Some(prev) => {
+                ((!raw.has_alpha || raw.blend == BlendMethod::NoBlend)
+                    && raw.is_full_canvas(canvas_width, canvas_height))
+                    || (prev.dispose == DisposeMethod::Background
+                        && (prev.is_full_canvas(canvas_width, canvas_height) || prev.keyframe))
+            }
+        };
+        let meta = FrameMeta {
+            x_offset: raw.x_offset,
+            y_offset: raw.y_offset,
+            width: raw.width,
+            height: raw.height,
+            duration_ms: raw.duration_ms,
+            blend: raw.blend,
+            dispose: raw.dispose,
+            has_alpha: raw.has_alpha,
+            keyframe,
+            start_ms,
+            bitstream: offset..offset + raw.bitstream.len(),
+        };
+        start_ms = meta.end_ms();
+        frames.push(meta);
+    }
+    frames
+}
+
+/// Canvas state for compositing frames in display order.
+pub(crate) struct Compositor {
+    width: u32,
+    height: u32,
+    mode: libwebp_sys::WEBP_CSP_MODE,
+    use_threads: bool,
+    /// Canvas after the last rendered frame.
+    curr: Vec<u8>,
+    /// Canvas after the last rendered frame was disposed.
+    prev_disposed: Vec<u8>,
+}
+
+impl Compositor {
+    /// Create a compositor. Canvas buffers are allocated on first use.
+    pub(crate) fn new(
+        width: u32,
+        height: u32,
+        mode: libwebp_sys::WEBP_CSP_MODE,
+        use_threads: bool,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            mode,
+            use_threads,
+            curr: Vec::new(),
+            prev_disposed: Vec::new(),
+        }
+    }
+
+    /// Size of one canvas in bytes.
+    pub(crate) fn canvas_size(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+
+    /// The canvas after the last rendered frame.
+    pub(crate) fn canvas(&self) -> &[u8] {
+        &self.curr
+    }
+
+    pub(crate) fn mode(&self) -> libwebp_sys::WEBP_CSP_MODE {
+        self.mode
+    }
+
+    pub(crate) fn premultiplied(&self) -> bool {
+        matches!(
+            self.mode,
+            libwebp_sys::WEBP_CSP_MODE::MODE_rgbA | libwebp_sys::WEBP_CSP_MODE::MODE_bgrA
+        )
+    }
+
+    /// Switch between an output mode with premultiplied alpha and the same
+    /// channel order with straight alpha. The canvas is discarded if the
+    /// mode changes.
+    pub(crate) fn set_premultiplied(&mut self, premultiplied: bool) {
+        use libwebp_sys::WEBP_CSP_MODE::*;
+        let mode = match (self.mode, premultiplied) {
+            (MODE_RGBA, true) => MODE_rgbA,
+            (MODE_BGRA, true) => MODE_bgrA,
+            (MODE_rgbA, false) => MODE_RGBA,
+            (MODE_bgrA, false) => MODE_BGRA,
+            _ => return,
+        };
+        self.mode = mode;
+        self.curr = Vec::new();
+        self.prev_disposed = Vec::new();
+    }
+
+    fn allocate(&mut self) -> Result<()> {
+        if !self.curr.is_empty() {
+            return Ok(());
+        }
+        let size = self.canvas_size();
+        for buf in [&mut self.curr, &mut self.prev_disposed] {
+            buf.try_reserve_exact(size)
+                .map_err(|_| at!(Error::OutOfMemory))?;
+            buf.resize(size, 0);
+        }
+        Ok(())
+    }
+
+    /// Render `frames[index]` on top of the canvas left by `frames[index - 1]`.
+    ///
+    /// The previous frame must have been rendered last unless `frames[index]`
+    /// is a keyframe.
+    pub(crate) fn render(&mut self, data: &[u8], frames: &[FrameMeta], index: usize) -> Result<()> {
+        let frame = &frames[index];
+        let bitstream = data
+            .get(frame.bitstream.clone())
+            .ok_or_else(|| at!(Error::InvalidWebP))?;
+        if frame.x_offset + frame.width > self.width || frame.y_offset + frame.height > self.height
+        {
+            return Err(at!(Error::InvalidWebP));
+        }
+        self.allocate()?;
+
+        if frame.keyframe {
+            self.curr.fill(0);
+        } else {
+            self.curr.copy_from_slice(&self.prev_disposed);
+        }
+
+        self.decode_into_canvas(bitstream, frame)?;
+
+        if index > 0 && frame.blend == BlendMethod::AlphaBlend && !frame.keyframe {
+            let prev = &frames[index - 1];
+            for y in frame.y_offset..frame.y_offset + frame.height {
+                if prev.dispose == DisposeMethod::None {
+                    self.blend_row(y, frame.x_offset, frame.width);
+                } else {
+                    // Pixels inside the disposed rectangle blend against
+                    // transparent, which leaves them unchanged.
+                    let (left1, width1, left2, width2) = blend_range_at_row(frame, prev, y);
+                    if width1 > 0 {
+                        self.blend_row(y, left1, width1);
+                    }
+                    if width2 > 0 {
+                        self.blend_row(y, left2, width2);
+                    }
+                }
+            }
+        }
+
+        self.prev_disposed.copy_from_slice(&self.curr);
+        if frame.dispose == DisposeMethod::Background {
+            let stride = self.width as usize * 4;
+            for y in frame.y_offset..frame.y_offset + frame.height {
+                let start = y as usize * stride + frame.x_offset as usize * 4;
+                self.prev_disposed[start..start + frame.width as usize * 4].fill(0);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_into_canvas(&mut self, bitstream: &[u8], frame: &FrameMeta) -> Result<()> {
+        let mut config = libwebp_sys::WebPDecoderConfig::new()
+            .map_err(|_| at!(Error::InvalidConfig("failed to init decoder config".into())))?;
+
+        let stride = self.width as usize * 4;
+        let offset = frame.y_offset as usize * stride + frame.x_offset as usize * 4;
+        let out = &mut self.curr[offset..];
+
+        config.options.use_threads = self.use_threads as i32;
+        config.output.colorspace = self.mode;
+        config.output.is_external_memory = 1;
+        unsafe {
+            let rgba = &mut config.output.u.RGBA;
+            rgba.rgba = out.as_mut_ptr();
+            rgba.stride = stride as i32;
+            rgba.size = out.len();
+        }
+
+        let status =
+            unsafe { libwebp_sys::WebPDecode(bitstream.as_ptr(), bitstream.len(), &mut config) };
+        if status != libwebp_sys::VP8StatusCode::VP8_STATUS_OK {
+            return Err(at!(Error::DecodeFailed(DecodingError::from(status as i32))));
+        }
+        Ok(())
+    }
+
+    /// Blend `width` pixels of row `y`, starting at `left`, over the disposed canvas.
+    fn blend_row(&mut self, y: u32, left: u32, width: u32) {
+        let start = (y as usize * self.width as usize + left as usize) * 4;
+        let end = start + width as usize * 4;
+        let premultiplied = self.premultiplied();
+        let src = self.curr[start..end].chunks_exact_mut(4);
+        let dst = self.prev_disposed[start..end].chunks_exact(4);
+        for (s, d) in src.zip(dst) {
+            if s[3] == 0xff {
+                continue;
+            }
+            let src_px = u32::from_le_bytes([s[0], s[1], s[2], s[3]]);
+            let dst_px = u32::from_le_bytes([d[0], d[1], d[2], d[3]]);
+            let blended = if premultiplied {
+                blend_pixel_premult(src_px, dst_px)
+            } else {
+                blend_pixel_non_premult(src_px, dst_px)
+            };
+            s.copy_from_slice(&blended.to_le_bytes());
+        }
+    }
+}
+
+/// Ranges of row `y` that belong to `src` but not to the disposed `dst` rect.
+fn blend_range_at_row(src: &FrameMeta, dst: &FrameMeta, y: u32) -> (u32, u32, u32, u32) {
+    let src_max_x = src.x_offset + src.width;
+    let dst_max_x = dst.x_offset + dst.width;
+    let dst_max_y = dst.y_offset + dst.height;
+
+    if y < dst.y_offset || y >= dst_max_y || src.x_offset >= dst_max_x || src_max_x <= dst.x_offset
+    {
+        return (src.x_offset, src.width, 0, 0);
+    }
+
+    let (mut left1, mut width1, mut left2, mut width2) = (0, 0, 0, 0);
+    if src.x_offset < dst.x_offset {
+        left1 = src.x_offset;
+        width1 = dst.x_offset - src.x_offset;
+    }
+    if src_max_x > dst_max_x {
+        left2 = dst_max_x;
+        width2 = src_max_x - dst_max_x;
+    }
+    (left1, width1, left2, width2)
+}
+
+/// Blend one channel of non-premultiplied pixels (libwebp's integer formula).
+fn blend_channel_non_premult(
+    src: u32,
+    src_a: u32,
+    dst: u32,
+    dst_a: u32,
+    scale: u32,
+    shift: u32,
+) -> u32 {
+    let src_channel = (src >> shift) & 0xff;
+    let dst_channel = (dst >> shift) & 0xff;
+    let blend_unscaled = src_channel * src_a + dst_channel * dst_a;
+    ((blend_unscaled * scale) >> 24) & 0xff
+}
+
+/// Blend `src` over `dst`, neither premultiplied, alpha in the top byte.
+fn blend_pixel_non_premult(src: u32, dst: u32) -> u32 {
+    let src_a = src >> 24;
+    if src_a == 0 {
+        return dst;
+    }
+    let dst_a = dst >> 24;
+    let dst_factor_a = (dst_a * (256 - src_a)) >> 8;
+    let blend_a = src_a + dst_factor_a;
+    let scale = (1u32 << 24) / blend_a;
+
+    let r = blend_channel_non_premult(src, src_a, dst, dst_factor_a, scale, 0);
+    let g = blend_channel_non_premult(src, src_a, dst, dst_factor_a, scale, 8);
+    let b = blend_channel_non_premult(src, src_a, dst, dst_factor_a, scale, 16);
+    r | (g << 8) | (b << 16) | (blend_a << 24)
+}
+
+/// Blend premultiplied `src` over `dst`.
+fn blend_pixel_premult(src: u32, dst: u32) -> u32 {
+    let scale = 256 - (src >> 24);
+    let mask = 0x00ff_00ff;
+    let rb = ((dst & mask) * scale) >> 8;
+    let ag = ((dst >> 8) & mask) * scale;
+    src.wrapping_add((rb & mask) | (ag & !mask))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        AnimationBuilder, AnimationDecoder, AnimationEncoder, AnimationEncoderOptions, BlendMethod,
+        ColorMode, DisposeMethod, EncoderConfig, Unstoppable,
+    };
+    use alloc::vec::Vec;
+    use libwebp_sys::WEBP_CSP_MODE;
+
+    /// Decode every frame with libwebp's `WebPAnimDecoder`, as (canvas, timestamp).
+    fn libwebp_frames(data: &[u8], mode: WEBP_CSP_MODE) -> Vec<(Vec<u8>, i32)> {
+        let mut options = core::mem::MaybeUninit::<libwebp_sys::WebPAnimDecoderOptions>::uninit();
+        assert!(unsafe { libwebp_sys::WebPAnimDecoderOptionsInit(options.as_mut_ptr()) } != 0);
+        let mut options = unsafe { options.assume_init() };
+        options.color_mode = mode;
+        let webp_data = libwebp_sys::WebPData {
+            bytes: data.as_ptr(),
+            size: data.len(),
+        };
+        let decoder = unsafe { libwebp_sys::WebPAnimDecoderNew(&webp_data, &options) };
+        assert!(!decoder.is_null());
+        let mut info = unsafe { core::mem::zeroed::<libwebp_sys::WebPAnimInfo>() };
+        assert!(unsafe { libwebp_sys::WebPAnimDecoderGetInfo(decoder, &mut info) } != 0);
+        let size = info.canvas_width as usize * info.canvas_height as usize * 4;
+
+        let mut frames = Vec::new();
+        while unsafe { libwebp_sys::WebPAnimDecoderHasMoreFrames(decoder) } != 0 {
+            let mut buf = core::ptr::null_mut();
+            let mut timestamp = 0;
+            let ok =
+                unsafe { libwebp_sys::WebPAnimDecoderGetNext(decoder, &mut buf, &mut timestamp) };
+            assert!(ok != 0);
+            let canvas = unsafe { core::slice::from_raw_parts(buf, size) }.to_vec();
+            frames.push((canvas, timestamp));
+        }
+        unsafe { libwebp_sys::WebPAnimDecoderDelete(decoder) };
+        frames
+    }
+
+    /// RGBA pixels with every kind of alpha, varying with `seed`.
+    fn pattern(width: u32, height: u32, seed: u32) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let alpha = [0, 1, 64, 128, 200, 254, 255][((x + 2 * y + seed) % 7) as usize];
+                pixels.extend_from_slice(&[
+                    (x * 37 + seed * 11) as u8,
+                    (y * 53 + seed * 7) as u8,
+                    (x * y + seed * 29) as u8,
+                    alpha,
+                ]);
+            }
+        }
+        pixels
+    }
+
+    /// Frame size, offset, blend and dispose method.
+    type Layout = ((u32, u32), (u32, u32), BlendMethod, DisposeMethod);
+
+    /// Assemble frames with the given layouts and varying content.
+    fn assemble(canvas: (u32, u32), frames: &[Layout], config: &EncoderConfig) -> Vec<u8> {
+        let mut builder = AnimationBuilder::new(canvas.0, canvas.1).unwrap();
+        for (seed, &((width, height), offset, blend, dispose)) in frames.iter().enumerate() {
+            let still = config
+                .encode_rgba(
+                    &pattern(width, height, seed as u32),
+                    width,
+                    height,
+                    Unstoppable,
+                )
+                .unwrap();
+            builder
+                .push_encoded(&still, 40 + seed as u32 * 10, offset, blend, dispose)
+                .unwrap();
+        }
+        builder.build().unwrap()
+    }
+
+    fn fixtures() -> Vec<Vec<u8>> {
+        use BlendMethod::{AlphaBlend as Blend, NoBlend};
+        use DisposeMethod::{Background as Clear, None as Keep};
+
+        // Overlapping sub-rectangles with odd sizes, blending against
+        // partly disposed areas, and a full-canvas disposal making the
+        // next sub-rectangle a keyframe
+        let overlapping = [
+            ((24, 20), (0, 0), NoBlend, Keep),
+            ((11, 9), (2, 2), Blend, Clear),
+            ((9, 7), (6, 4), Blend, Keep),
+            ((5, 11), (0, 8), Blend, Clear),
+            ((13, 5), (4, 10), Blend, Clear),
+            ((15, 3), (2, 12), NoBlend, Keep),
+            ((24, 20), (0, 0), Blend, Clear),
+            ((7, 7), (8, 6), Blend, Keep),
+            ((11, 3), (12, 16), Blend, Keep),
+        ];
+        // Starts with a sub-rectangle keyframe that clears itself, so the
+        // next frame is a keyframe too
+        let sub_keyframes = [
+            ((9, 9), (4, 4), Blend, Clear),
+            ((7, 5), (10, 2), Blend, Clear),
+            ((24, 20), (0, 0), Blend, Keep),
+            ((3, 17), (20, 2), Blend, Clear),
+            ((21, 1), (2, 18), Blend, Keep),
+        ];
+
+        let lossless = EncoderConfig::new().lossless(true).exact(true);
+        let lossy = EncoderConfig::new().quality(70.0);
+        let mut fixtures = alloc::vec![
+            assemble((24, 20), &overlapping, &lossless),
+            assemble((24, 20), &overlapping, &lossy),
+            assemble((24, 20), &sub_keyframes, &lossless),
+        ];
+
+        // Whatever libwebp's own encoder chooses, with frequent keyframes
+        let options = AnimationEncoderOptions::new().kmin(2).kmax(3);
+        let mut encoder = AnimationEncoder::from_options(24, 20, &options).unwrap();
+        for i in 0..8u32 {
+            let mut frame = pattern(24, 20, 0);
+            for (p, px) in frame.chunks_exact_mut(4).enumerate() {
+                let (x, y) = (p as u32 % 24, p as u32 / 24);
+                if (i * 2..i * 2 + 9).contains(&x) && (3..11).contains(&y) {
+                    px.copy_from_slice(&[255, 30 * i as u8, 0, 100 + 20 * i as u8]);
+                }
+            }
+            encoder.add_frame_rgba(&frame, i as i32 * 30).unwrap();
+        }
+        fixtures.push(encoder.finish(240).unwrap());
+        fixtures
+    }
+
+    #[test]
+    fn test_matches_libwebp_anim_decoder() {
+        let modes = [
+            (ColorMode::Rgba, WEBP_CSP_MODE::MODE_RGBA),
+            (ColorMode::Bgra, WEBP_CSP_MODE::MODE_BGRA),
+            (ColorMode::RgbaPremultiplied, WEBP_CSP_MODE::MODE_rgbA),
+            (ColorMode::BgraPremultiplied, WEBP_CSP_MODE::MODE_bgrA),
+        ];
+        for (f, webp) in fixtures().iter().enumerate() {
+            for (color_mode, csp_mode) in modes {
+                let expected = libwebp_frames(webp, csp_mode);
+                assert!(expected.len() > 1);
+
+                let mut decoder = AnimationDecoder::with_options(webp, color_mode, false).unwrap();
+                let frames = decoder.decode_all().unwrap();
+                assert_eq!(frames.len(), expected.len());
+                for (i, (frame, (canvas, timestamp))) in frames.iter().zip(&expected).enumerate() {
+                    assert_eq!(frame.timestamp_ms, *timestamp);
+                    assert!(
+                        frame.data == *canvas,
+                        "fixture {} {:?} frame {}",
+                        f,
+                        color_mode,
+                        i
+                    );
+                }
+
+                // Seeking starts from the nearest keyframe instead of frame 0
+                for i in (0..expected.len()).rev() {
+                    let frame = decoder.seek_to_frame(i as u32).unwrap();
+                    assert!(
+                        frame.data == expected[i].0,
+                        "fixture {} {:?} seek {}",
+                        f,
+                        color_mode,
+                        i
+                    );
+                }
+            }
+        }
+    }
+}
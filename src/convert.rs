@@ -0,0 +1,377 @@
+//! Conversion between animated WebP and other animation formats.
+
+use crate::animation::AnimationDecoder;
+#[cfg(feature = "gif")]
+use crate::animation::{AnimationEncoder, AnimationEncoderOptions};
+#[cfg(feature = "gif")]
+use crate::config::EncoderConfig;
+use crate::error::{Error, Result};
+use alloc::vec::Vec;
+#[cfg(feature = "gif")]
+use enough::{Stop, Unstoppable};
+use whereat::*;
+
+/// Convert an animated (or still) GIF to animated WebP.
+///
+/// Frames are composited onto a transparent canvas following the GIF
+/// disposal methods, and each full canvas is handed to [`AnimationEncoder`],
+/// which finds the changed sub-rectangles again. These rules follow
+/// libwebp's `gif2webp`:
+///
+/// - Delays of 10ms or less (including 0) become 100ms, as in browsers.
+/// - A GIF without a loop extension plays once; a GIF loop count of `n`
+///   means `n` repetitions, so the WebP loop count is `n + 1`.
+/// - The background color is the global palette entry at the GIF's
+///   background index, transparent if the first frame uses that index as
+///   its transparent color, and opaque white otherwise.
+/// - If `options` leaves the keyframe interval at its default, `gif2webp`'s
+///   defaults are used: `kmin = 9, kmax = 17` for lossless and
+///   `kmin = 3, kmax = 5` for lossy.
+///
+/// The loop count and background color in `options` are replaced by the
+/// GIF's; everything else in `options` and `config` is used as given. In
+/// particular [`allow_mixed`](AnimationEncoderOptions::allow_mixed) keeps
+/// its default of `true`, while `gif2webp` defaults to `false`. To get
+/// `gif2webp`'s default output, pass a lossless `config` with
+/// `allow_mixed(false)`; `-lossy` is a lossy `config`, `-mixed` is
+/// `allow_mixed(true)`, and `-min_size` is
+/// [`minimize_size(true)`](AnimationEncoderOptions::minimize_size).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::{gif_to_webp, AnimationEncoderOptions, EncoderConfig};
+///
+/// let gif = std::fs::File::open("sticker.gif").unwrap();
+/// let options = AnimationEncoderOptions::new().allow_mixed(false);
+/// let webp = gif_to_webp(gif, &options, &EncoderConfig::new().lossless(true))?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "gif")]
+pub fn gif_to_webp<R: std::io::Read>(
+    reader: R,
+    options: &AnimationEncoderOptions,
+    config: &EncoderConfig,
+) -> Result<Vec<u8>> {
+    gif_to_webp_stoppable(reader, options, config, Unstoppable)
+}
+
+/// Convert a GIF to animated WebP with cooperative cancellation.
+///
+/// Same as [`gif_to_webp`], with `stop` checked before and during the
+/// encoding of each frame (see [`AnimationEncoder::set_stop`]).
+#[cfg(feature = "gif")]
+pub fn gif_to_webp_stoppable<R: std::io::Read>(
+    reader: R,
+    options: &AnimationEncoderOptions,
+    config: &EncoderConfig,
+    stop: impl Stop + 'static,
+) -> Result<Vec<u8>> {
+    stop.check().map_err(|reason| at!(Error::Stopped(reason)))?;
+    let mut stop = Some(stop);
+
+    let mut decode_options = gif::DecodeOptions::new();
+    decode_options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decode_options.read_info(reader).map_err(gif_error)?;
+
+    let width = decoder.width() as u32;
+    let height = decoder.height() as u32;
+    // The screen size is untrusted, so check it before allocating canvases
+    if width == 0 || height == 0 || width > 16383 || height > 16383 {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "GIF size {}x{} is outside 1x1 to 16383x16383",
+            width,
+            height
+        ))));
+    }
+
+    let mut options = options.clone();
+    let defaults = AnimationEncoderOptions::default();
+    if options.kmin == defaults.kmin && options.kmax == defaults.kmax {
+        options = if config.lossless {
+            options.kmin(9).kmax(17)
+        } else {
+            options.kmin(3).kmax(5)
+        };
+    }
+
+    // Frames borrow the decoder, so read what the first frame needs up front
+    let background_index = decoder.bg_color();
+    let global_palette = decoder.global_palette().map(<[u8]>::to_vec);
+
+    let canvas_size = width as usize * height as usize * 4;
+    let mut canvas = Vec::new();
+    let mut previous = Vec::new();
+    for buf in [&mut canvas, &mut previous] {
+        buf.try_reserve_exact(canvas_size)
+            .map_err(|_| at!(Error::OutOfMemory))?;
+        buf.resize(canvas_size, 0);
+    }
+    let mut encoder: Option<AnimationEncoder> = None;
+    let mut timestamp_ms = 0i32;
+
+    while let Some(frame) = decoder.read_next_frame().map_err(gif_error)? {
+        let encoder = match encoder.as_mut() {
+            Some(encoder) => encoder,
+            None => {
+                let bgcolor = gif_background_color(
+                    global_palette.as_deref(),
+                    background_index,
+                    frame.transparent,
+                );
+                let mut new_encoder = AnimationEncoder::from_options(
+                    width,
+                    height,
+                    &options.clone().bgcolor(bgcolor),
+                )?;
+                new_encoder.set_config(config.clone())?;
+                if let Some(stop) = stop.take() {
+                    new_encoder.set_stop(stop);
+                }
+                encoder.insert(new_encoder)
+            }
+        };
+
+        let rect = GifRect::new(frame, width, height);
+        rect.blend(&frame.buffer, frame.width as usize, &mut canvas, width);
+        encoder.add_frame_rgba(&canvas, timestamp_ms)?;
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => rect.clear(&mut canvas, width),
+            gif::DisposalMethod::Previous => rect.restore(&previous, &mut canvas, width),
+            _ => {}
+        }
+        previous.copy_from_slice(&canvas);
+
+        let mut duration_ms = frame.delay as i32 * 10;
+        if duration_ms <= 10 {
+            duration_ms = 100;
+        }
+        timestamp_ms = timestamp_ms.saturating_add(duration_ms);
+    }
+
+    let encoder = encoder.ok_or_else(|| at!(Error::InvalidInput("GIF has no frames".into())))?;
+    let webp = encoder.finish(timestamp_ms)?;
+    if !crate::ImageInfo::from_webp(&webp)?.has_animation {
+        // A single frame, or frames libwebp merged because they were
+        // identical, is written as a still image, which has no loop count
+        return Ok(webp);
+    }
+
+    // Like gif2webp, store the loop count once the whole GIF has been read
+    let loop_count = match decoder.repeat() {
+        gif::Repeat::Infinite => 0,
+        gif::Repeat::Finite(0) => 1,
+        gif::Repeat::Finite(n) => (n as u32 + 1).min(0xffff),
+    };
+    crate::mux::set_loop_count(&webp, loop_count)
+}
+
+/// Background color of a GIF as ARGB, following `gif2webp`.
+#[cfg(feature = "gif")]
+fn gif_background_color(
+    palette: Option<&[u8]>,
+    index: Option<usize>,
+    transparent: Option<u8>,
+) -> u32 {
+    match (index, palette) {
+        (Some(index), _) if transparent.map(usize::from) == Some(index) => 0,
+        (Some(index), Some(palette)) if index * 3 + 2 < palette.len() => {
+            let rgb = &palette[index * 3..index * 3 + 3];
+            0xff00_0000 | (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32
+        }
+        _ => 0xffff_ffff,
+    }
+}
+
+#[cfg(feature = "gif")]
+fn gif_error(err: gif::DecodingError) -> At<Error> {
+    match err {
+        gif::DecodingError::Io(err) => at!(Error::IoError(err.to_string())),
+        gif::DecodingError::OutOfMemory => at!(Error::OutOfMemory),
+        err => at!(Error::InvalidInput(alloc::format!("invalid GIF: {}", err))),
+    }
+}
+
+/// A GIF frame rectangle clipped to the canvas.
+#[cfg(feature = "gif")]
+struct GifRect {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+#[cfg(feature = "gif")]
+impl GifRect {
+    fn new(frame: &gif::Frame<'_>, canvas_width: u32, canvas_height: u32) -> Self {
+        let left = (frame.left as usize).min(canvas_width as usize);
+        let top = (frame.top as usize).min(canvas_height as usize);
+        Self {
+            left,
+            top,
+            width: (frame.width as usize).min(canvas_width as usize - left),
+            height: (frame.height as usize).min(canvas_height as usize - top),
+        }
+    }
+
+    /// Byte range of row `y` of the rectangle within the canvas.
+    fn row(&self, y: usize, canvas_width: u32) -> core::ops::Range<usize> {
+        let start = ((self.top + y) * canvas_width as usize + self.left) * 4;
+        start..start + self.width * 4
+    }
+
+    /// Draw the non-transparent pixels of `pixels` onto the canvas.
+    fn blend(&self, pixels: &[u8], stride: usize, canvas: &mut [u8], canvas_width: u32) {
+        for y in 0..self.height {
+            let src = &pixels[y * stride * 4..][..self.width * 4];
+            let dst = &mut canvas[self.row(y, canvas_width)];
+            for (d, s) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                if s[3] != 0 {
+                    d.copy_from_slice(s);
+                }
+            }
+        }
+    }
+
+    fn clear(&self, canvas: &mut [u8], canvas_width: u32) {
+        for y in 0..self.height {
+            canvas[self.row(y, canvas_width)].fill(0);
+        }
+    }
+
+    fn restore(&self, previous: &[u8], canvas: &mut [u8], canvas_width: u32) {
+        for y in 0..self.height {
+            let row = self.row(y, canvas_width);
+            canvas[row.clone()].copy_from_slice(&previous[row]);
+        }
+    }
+}
+
+/// Convert an animated (or still) WebP to an animated GIF.
+///
+/// Each composited frame is quantized to its own 256-color palette. GIF
+/// only has 1-bit transparency, so any non-zero alpha becomes opaque.
+/// Frame start and end times are rounded to the GIF's 10ms resolution,
+/// so rounding errors don't add up and the total length is kept. Delays
+/// are not raised to a minimum, though browsers play delays under 20ms
+/// slower. The loop count is kept
+/// (a WebP loop count of `n` plays `n` times, written as `n - 1` GIF
+/// repetitions).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// let gif = webpx::webp_to_gif(webp_data)?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "gif")]
+pub fn webp_to_gif(webp_data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = AnimationDecoder::new(webp_data)?;
+    let info = decoder.info().clone();
+    let (width, height) = gif_dimensions(info.width, info.height)?;
+
+    let mut output = Vec::new();
+    {
+        let mut encoder =
+            gif::Encoder::new(&mut output, width, height, &[]).map_err(gif_encode_error)?;
+        let repeat = match info.loop_count {
+            0 => gif::Repeat::Infinite,
+            n => gif::Repeat::Finite((n - 1).min(u16::MAX as u32) as u16),
+        };
+        encoder.set_repeat(repeat).map_err(gif_encode_error)?;
+
+        let centiseconds = |ms: i64| (ms + 5).div_euclid(10);
+        while let Some(mut frame) = decoder.next_frame()? {
+            let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut frame.data, 10);
+            let end_ms = frame.timestamp_ms as i64;
+            let start_ms = end_ms - frame.duration_ms as i64;
+            gif_frame.delay =
+                (centiseconds(end_ms) - centiseconds(start_ms)).clamp(0, u16::MAX as i64) as u16;
+            // Every frame covers the canvas, so clear it for transparent pixels
+            gif_frame.dispose = gif::DisposalMethod::Background;
+            encoder.write_frame(&gif_frame).map_err(gif_encode_error)?;
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(feature = "gif")]
+fn gif_dimensions(width: u32, height: u32) -> Result<(u16, u16)> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(at!(Error::InvalidInput(alloc::format!(
+            "{}x{} exceeds the GIF size limit of 65535x65535",
+            width,
+            height
+        )))),
+    }
+}
+
+#[cfg(feature = "gif")]
+fn gif_encode_error(err: gif::EncodingError) -> At<Error> {
+    match err {
+        gif::EncodingError::Io(err) => at!(Error::IoError(err.to_string())),
+        gif::EncodingError::OutOfMemory => at!(Error::OutOfMemory),
+        err => at!(Error::InvalidInput(alloc::format!(
+            "GIF encoding failed: {}",
+            err
+        ))),
+    }
+}
+
+/// Convert an animated (or still) WebP to an animated PNG (APNG).
+///
+/// Frames are written as full 8-bit RGBA canvases, so colors and alpha
+/// are kept exactly. Frame durations and the loop count (0 = infinite) map
+/// directly onto APNG's.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// let apng = webpx::webp_to_apng(webp_data)?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "apng")]
+pub fn webp_to_apng(webp_data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = AnimationDecoder::new(webp_data)?;
+    let info = decoder.info().clone();
+
+    let mut output = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut output, info.width, info.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(info.frame_count, info.loop_count)
+            .map_err(png_error)?;
+        let mut writer = encoder.write_header().map_err(png_error)?;
+
+        while let Some(frame) = decoder.next_frame()? {
+            // The delay fraction is 16-bit; fall back to centiseconds for long frames
+            let (numerator, denominator) = match u16::try_from(frame.duration_ms) {
+                Ok(ms) => (ms, 1000),
+                Err(_) => ((frame.duration_ms / 10).min(u16::MAX as u32) as u16, 100),
+            };
+            writer
+                .set_frame_delay(numerator, denominator)
+                .map_err(png_error)?;
+            writer.write_image_data(&frame.data).map_err(png_error)?;
+        }
+        writer.finish().map_err(png_error)?;
+    }
+    Ok(output)
+}
+
+#[cfg(feature = "apng")]
+fn png_error(err: png::EncodingError) -> At<Error> {
+    match err {
+        png::EncodingError::IoError(err) => at!(Error::IoError(err.to_string())),
+        err => at!(Error::InvalidInput(alloc::format!(
+            "PNG encoding failed: {}",
+            err
+        ))),
+    }
+}
@@ -0,0 +1,334 @@
+//! Raw (uncomposited) animation frame access through the demux API.
+
+use crate::error::{Error, Result};
+use crate::types::BitstreamFormat;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use whereat::*;
+
+/// How a frame is combined with the canvas beneath it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMethod {
+    /// Alpha-blend the frame over the existing canvas.
+    #[default]
+    AlphaBlend,
+    /// Overwrite the frame rectangle, ignoring the existing canvas.
+    NoBlend,
+}
+
+impl BlendMethod {
+    pub(crate) fn from_libwebp(blend: libwebp_sys::WebPMuxAnimBlend) -> Self {
+        match blend {
+            libwebp_sys::WebPMuxAnimBlend::WEBP_MUX_NO_BLEND => BlendMethod::NoBlend,
+            _ => BlendMethod::AlphaBlend,
+        }
+    }
+
+    pub(crate) fn to_libwebp(self) -> libwebp_sys::WebPMuxAnimBlend {
+        match self {
+            BlendMethod::AlphaBlend => libwebp_sys::WebPMuxAnimBlend::WEBP_MUX_BLEND,
+            BlendMethod::NoBlend => libwebp_sys::WebPMuxAnimBlend::WEBP_MUX_NO_BLEND,
+        }
+    }
+}
+
+/// What happens to a frame's rectangle after it has been displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisposeMethod {
+    /// Leave the canvas as is.
+    #[default]
+    None,
+    /// Clear the frame rectangle to the background (transparent) color.
+    Background,
+}
+
+impl DisposeMethod {
+    pub(crate) fn from_libwebp(dispose: libwebp_sys::WebPMuxAnimDispose) -> Self {
+        match dispose {
+            libwebp_sys::WebPMuxAnimDispose::WEBP_MUX_DISPOSE_BACKGROUND => {
+                DisposeMethod::Background
+            }
+            _ => DisposeMethod::None,
+        }
+    }
+
+    pub(crate) fn to_libwebp(self) -> libwebp_sys::WebPMuxAnimDispose {
+        match self {
+            DisposeMethod::None => libwebp_sys::WebPMuxAnimDispose::WEBP_MUX_DISPOSE_NONE,
+            DisposeMethod::Background => {
+                libwebp_sys::WebPMuxAnimDispose::WEBP_MUX_DISPOSE_BACKGROUND
+            }
+        }
+    }
+}
+
+/// A single animation frame as stored in the file, before compositing.
+///
+/// `bitstream` borrows the input data and holds the frame's `ALPH` (if any)
+/// and `VP8 `/`VP8L` chunks. It can be decoded on its own with the still
+/// image decoder, or uploaded as a sub-rectangle by a custom compositor.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct RawFrame<'a> {
+    /// Frame index (0-based).
+    pub index: u32,
+    /// Horizontal offset of the frame on the canvas.
+    pub x_offset: u32,
+    /// Vertical offset of the frame on the canvas.
+    pub y_offset: u32,
+    /// Frame width.
+    pub width: u32,
+    /// Frame height.
+    pub height: u32,
+    /// Frame duration in milliseconds.
+    pub duration_ms: u32,
+    /// Blend method against the previous canvas.
+    pub blend: BlendMethod,
+    /// Dispose method applied after the frame is displayed.
+    pub dispose: DisposeMethod,
+    /// Whether the frame contains alpha.
+    pub has_alpha: bool,
+    /// Whether the frame is lossy (VP8) or lossless (VP8L).
+    pub format: BitstreamFormat,
+    /// Frame bitstream (`ALPH` + `VP8 ` or `VP8L` chunks).
+    pub bitstream: &'a [u8],
+}
+
+impl RawFrame<'_> {
+    /// Whether the frame covers the whole canvas.
+    pub fn is_full_canvas(&self, canvas_width: u32, canvas_height: u32) -> bool {
+        self.x_offset == 0
+            && self.y_offset == 0
+            && self.width == canvas_width
+            && self.height == canvas_height
+    }
+
+    fn from_iter(iter: &libwebp_sys::WebPIterator) -> Self {
+        let bitstream = if iter.fragment.bytes.is_null() {
+            &[][..]
+        } else {
+            unsafe { core::slice::from_raw_parts(iter.fragment.bytes, iter.fragment.size) }
+        };
+        RawFrame {
+            index: (iter.frame_num - 1).max(0) as u32,
+            x_offset: iter.x_offset as u32,
+            y_offset: iter.y_offset as u32,
+            width: iter.width as u32,
+            height: iter.height as u32,
+            duration_ms: iter.duration.max(0) as u32,
+            blend: BlendMethod::from_libwebp(iter.blend_method),
+            dispose: DisposeMethod::from_libwebp(iter.dispose_method),
+            has_alpha: iter.has_alpha != 0,
+            format: bitstream_format(bitstream),
+            bitstream,
+        }
+    }
+}
+
+/// Detect lossy/lossless from a frame payload, skipping a leading `ALPH` chunk.
+fn bitstream_format(mut payload: &[u8]) -> BitstreamFormat {
+    while payload.len() >= 8 {
+        let size = u32::from_le_bytes([payload[4], payload[5], payload[6], payload[7]]) as usize;
+        match &payload[..4] {
+            b"VP8 " => return BitstreamFormat::Lossy,
+            b"VP8L" => return BitstreamFormat::Lossless,
+            _ => {
+                let skip = 8usize.saturating_add(size).saturating_add(size & 1);
+                payload = payload.get(skip..).unwrap_or(&[]);
+            }
+        }
+    }
+    BitstreamFormat::Undefined
+}
+
+/// Safe wrapper around a `WebPDemuxer` borrowing its input.
+pub(crate) struct Demuxer<'a> {
+    demux: *mut libwebp_sys::WebPDemuxer,
+    _data: PhantomData<&'a [u8]>,
+}
+
+// SAFETY: the demuxer is only read after creation and owns no thread-local state
+unsafe impl Send for Demuxer<'_> {}
+
+impl<'a> Demuxer<'a> {
+    /// Parse complete WebP data (still or animated).
+    pub(crate) fn new(data: &'a [u8]) -> Result<Self> {
+        let webp_data = libwebp_sys::WebPData {
+            bytes: data.as_ptr(),
+            size: data.len(),
+        };
+        let demux = unsafe {
+            libwebp_sys::WebPDemuxInternal(
+                &webp_data,
+                0,
+                core::ptr::null_mut(),
+                libwebp_sys::WEBP_DEMUX_ABI_VERSION as i32,
+            )
+        };
+        if demux.is_null() {
+            return Err(at!(Error::InvalidWebP));
+        }
+        Ok(Self {
+            demux,
+            _data: PhantomData,
+        })
+    }
+
+    fn get(&self, feature: libwebp_sys::WebPFormatFeature) -> u32 {
+        unsafe { libwebp_sys::WebPDemuxGetI(self.demux, feature) }
+    }
+
+    pub(crate) fn canvas_width(&self) -> u32 {
+        self.get(libwebp_sys::WebPFormatFeature::WEBP_FF_CANVAS_WIDTH)
+    }
+
+    pub(crate) fn canvas_height(&self) -> u32 {
+        self.get(libwebp_sys::WebPFormatFeature::WEBP_FF_CANVAS_HEIGHT)
+    }
+
+    pub(crate) fn loop_count(&self) -> u32 {
+        self.get(libwebp_sys::WebPFormatFeature::WEBP_FF_LOOP_COUNT)
+    }
+
+    pub(crate) fn bgcolor(&self) -> u32 {
+        self.get(libwebp_sys::WebPFormatFeature::WEBP_FF_BACKGROUND_COLOR)
+    }
+
+    pub(crate) fn frame_count(&self) -> u32 {
+        self.get(libwebp_sys::WebPFormatFeature::WEBP_FF_FRAME_COUNT)
+    }
+
+    /// Payload of the first chunk with the given FourCC, e.g. `b"ICCP"`.
+    ///
+    /// Returns `None` if the chunk is missing or empty.
+    #[cfg(feature = "icc")]
+    pub(crate) fn chunk(&self, fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+        let mut iter = MaybeUninit::<libwebp_sys::WebPChunkIterator>::zeroed();
+        let found = unsafe {
+            libwebp_sys::WebPDemuxGetChunk(
+                self.demux,
+                fourcc.as_ptr() as *const core::ffi::c_char,
+                1,
+                iter.as_mut_ptr(),
+            )
+        };
+        if found == 0 {
+            return None;
+        }
+        let mut iter = unsafe { iter.assume_init() };
+        let chunk = iter.chunk;
+        unsafe { libwebp_sys::WebPDemuxReleaseChunkIterator(&mut iter) };
+        if chunk.bytes.is_null() || chunk.size == 0 {
+            return None;
+        }
+        // SAFETY: the demuxer does not copy its input, so chunks point into it
+        Some(unsafe { core::slice::from_raw_parts(chunk.bytes, chunk.size) })
+    }
+}
+
+impl Drop for Demuxer<'_> {
+    fn drop(&mut self) {
+        unsafe { libwebp_sys::WebPDemuxDelete(self.demux) };
+    }
+}
+
+/// Iterator over the raw frames of a WebP file.
+///
+/// Unlike [`AnimationDecoder`](crate::AnimationDecoder), no pixels are
+/// decoded or composited: each [`RawFrame`] describes where a sub-rectangle
+/// goes on the canvas and how it blends, and borrows its bitstream from the
+/// input. Still images yield a single full-canvas frame.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::FrameIter;
+///
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// let frames = FrameIter::new(webp_data)?;
+/// println!("canvas: {}x{}", frames.canvas_width(), frames.canvas_height());
+///
+/// for frame in frames {
+///     println!(
+///         "frame {} at ({}, {}) {}x{}, {} ms, {:?}/{:?}, {} bytes",
+///         frame.index, frame.x_offset, frame.y_offset, frame.width, frame.height,
+///         frame.duration_ms, frame.blend, frame.dispose, frame.bitstream.len(),
+///     );
+/// }
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+pub struct FrameIter<'a> {
+    demuxer: Demuxer<'a>,
+    iter: libwebp_sys::WebPIterator,
+    started: bool,
+    remaining: u32,
+}
+
+// SAFETY: the iterator only reads the demuxer and the borrowed input
+unsafe impl Send for FrameIter<'_> {}
+
+impl<'a> FrameIter<'a> {
+    /// Parse WebP data and iterate over its frames.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let demuxer = Demuxer::new(data)?;
+        let remaining = demuxer.frame_count();
+        Ok(Self {
+            demuxer,
+            iter: unsafe { MaybeUninit::zeroed().assume_init() },
+            started: false,
+            remaining,
+        })
+    }
+
+    /// Canvas width.
+    pub fn canvas_width(&self) -> u32 {
+        self.demuxer.canvas_width()
+    }
+
+    /// Canvas height.
+    pub fn canvas_height(&self) -> u32 {
+        self.demuxer.canvas_height()
+    }
+
+    pub(crate) fn demuxer(&self) -> &Demuxer<'a> {
+        &self.demuxer
+    }
+}
+
+impl<'a> Iterator for FrameIter<'a> {
+    type Item = RawFrame<'a>;
+
+    fn next(&mut self) -> Option<RawFrame<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let ok = unsafe {
+            if self.started {
+                libwebp_sys::WebPDemuxNextFrame(&mut self.iter)
+            } else {
+                self.started = true;
+                libwebp_sys::WebPDemuxGetFrame(self.demuxer.demux, 1, &mut self.iter)
+            }
+        };
+        if ok == 0 {
+            self.remaining = 0;
+            return None;
+        }
+        self.remaining -= 1;
+        Some(RawFrame::from_iter(&self.iter))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl ExactSizeIterator for FrameIter<'_> {}
+
+impl Drop for FrameIter<'_> {
+    fn drop(&mut self) {
+        if self.started {
+            unsafe { libwebp_sys::WebPDemuxReleaseIterator(&mut self.iter) };
+        }
+    }
+}
@@ -61,6 +61,8 @@
 //! | `animation` | No | Animated WebP support |
 //! | `icc` | No | ICC/EXIF/XMP metadata |
 //! | `streaming` | No | Incremental processing |
+//! | `gif` | No | GIF to/from animated WebP conversion |
+//! | `apng` | No | Animated WebP to APNG conversion |
 //!
 //! ## no_std Support
 //!
@@ -101,7 +103,7 @@ mod decode;
 #[cfg(feature = "encode")]
 mod encode;
 
-#[cfg(feature = "icc")]
+#[cfg(any(feature = "icc", feature = "animation"))]
 mod mux;
 
 #[cfg(feature = "streaming")]
@@ -109,6 +111,13 @@ mod streaming;
 
 #[cfg(feature = "animation")]
 mod animation;
+#[cfg(feature = "animation")]
+mod compositor;
+#[cfg(feature = "animation")]
+mod demux;
+
+#[cfg(any(feature = "gif", feature = "apng"))]
+mod convert;
 
 pub mod heuristics;
 
@@ -142,11 +151,27 @@ pub use mux::{
     remove_xmp,
 };
 
+#[cfg(feature = "animation")]
+pub use mux::{
+    concat_animations, dedup_frames, scale_durations, set_bgcolor, set_frame_duration,
+    set_loop_count, trim_animation, AnimationBuilder,
+};
+
 #[cfg(feature = "streaming")]
 pub use streaming::{DecodeStatus, StreamingDecoder, StreamingEncoder};
 
 #[cfg(feature = "animation")]
-pub use animation::{AnimationDecoder, AnimationEncoder, AnimationInfo, Frame};
+pub use animation::{
+    extract_frame, resample_animation, split_animation, AnimationDecoder, AnimationEncoder,
+    AnimationEncoderOptions, AnimationFrameStats, AnimationInfo, AnimationLimits,
+    AnimationProgress, Frame, FrameRef, FrameTiming, ResampleOptions,
+};
+#[cfg(feature = "apng")]
+pub use convert::webp_to_apng;
+#[cfg(feature = "gif")]
+pub use convert::{gif_to_webp, gif_to_webp_stoppable, webp_to_gif};
+#[cfg(feature = "animation")]
+pub use demux::{BlendMethod, DisposeMethod, FrameIter, RawFrame};
 
 /// Library version information.
 pub fn version() -> (u32, u32, u32) {
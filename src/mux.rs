@@ -1,8 +1,23 @@
-//! WebP mux/demux operations for metadata (ICC, EXIF, XMP).
+//! WebP mux/demux operations for metadata (ICC, EXIF, XMP) and animation frames.
+//!
+//! Edits work on the container's chunks: frames are copied as they are and
+//! only re-encoded where a function says so.
 
+#[cfg(feature = "animation")]
+use crate::animation::{encode_canvas, AnimationDecoder};
+#[cfg(feature = "animation")]
+use crate::compositor::{scan_frames, FrameMeta};
+#[cfg(feature = "animation")]
+use crate::config::EncoderConfig;
+#[cfg(feature = "animation")]
+use crate::demux::{BlendMethod, DisposeMethod, FrameIter};
 use crate::error::{Error, MuxError, Result};
+#[cfg(feature = "animation")]
+use crate::types::ImageInfo;
 use alloc::vec::Vec;
 use core::mem::MaybeUninit;
+#[cfg(feature = "animation")]
+use core::ops::{Bound, RangeBounds};
 use whereat::*;
 
 /// Extract ICC profile from WebP data.
@@ -18,6 +33,7 @@ use whereat::*;
 /// }
 /// # Ok::<(), webpx::At<webpx::Error>>(())
 /// ```
+#[cfg(feature = "icc")]
 pub fn get_icc_profile(webp_data: &[u8]) -> Result<Option<Vec<u8>>> {
     get_chunk(webp_data, b"ICCP")
 }
@@ -25,6 +41,7 @@ pub fn get_icc_profile(webp_data: &[u8]) -> Result<Option<Vec<u8>>> {
 /// Extract EXIF metadata from WebP data.
 ///
 /// Returns `None` if no EXIF data is present.
+#[cfg(feature = "icc")]
 pub fn get_exif(webp_data: &[u8]) -> Result<Option<Vec<u8>>> {
     get_chunk(webp_data, b"EXIF")
 }
@@ -32,11 +49,13 @@ pub fn get_exif(webp_data: &[u8]) -> Result<Option<Vec<u8>>> {
 /// Extract XMP metadata from WebP data.
 ///
 /// Returns `None` if no XMP data is present.
+#[cfg(feature = "icc")]
 pub fn get_xmp(webp_data: &[u8]) -> Result<Option<Vec<u8>>> {
     get_chunk(webp_data, b"XMP ")
 }
 
 /// Helper to create a demuxer from WebP data.
+#[cfg(feature = "icc")]
 unsafe fn create_demux(webp_data: &[u8]) -> *mut libwebp_sys::WebPDemuxer {
     let data = libwebp_sys::WebPData {
         bytes: webp_data.as_ptr(),
@@ -70,6 +89,7 @@ unsafe fn create_mux_from_data(webp_data: &[u8], copy_data: bool) -> *mut libweb
 }
 
 /// Get a metadata chunk from WebP data.
+#[cfg(feature = "icc")]
 fn get_chunk(webp_data: &[u8], fourcc: &[u8; 4]) -> Result<Option<Vec<u8>>> {
     let demux = unsafe { create_demux(webp_data) };
 
@@ -116,21 +136,25 @@ fn get_chunk(webp_data: &[u8], fourcc: &[u8; 4]) -> Result<Option<Vec<u8>>> {
 /// Embed ICC profile into WebP data.
 ///
 /// Takes existing WebP data and adds or replaces the ICC profile.
+#[cfg(feature = "icc")]
 pub fn embed_icc(webp_data: &[u8], icc_profile: &[u8]) -> Result<Vec<u8>> {
     embed_chunk(webp_data, b"ICCP", icc_profile)
 }
 
 /// Embed EXIF metadata into WebP data.
+#[cfg(feature = "icc")]
 pub fn embed_exif(webp_data: &[u8], exif_data: &[u8]) -> Result<Vec<u8>> {
     embed_chunk(webp_data, b"EXIF", exif_data)
 }
 
 /// Embed XMP metadata into WebP data.
+#[cfg(feature = "icc")]
 pub fn embed_xmp(webp_data: &[u8], xmp_data: &[u8]) -> Result<Vec<u8>> {
     embed_chunk(webp_data, b"XMP ", xmp_data)
 }
 
 /// Embed a metadata chunk into WebP data.
+#[cfg(feature = "icc")]
 fn embed_chunk(webp_data: &[u8], fourcc: &[u8; 4], chunk_data: &[u8]) -> Result<Vec<u8>> {
     // Create mux from existing WebP data
     let mux = unsafe { create_mux_from_data(webp_data, true) };
@@ -184,21 +208,25 @@ fn embed_chunk(webp_data: &[u8], fourcc: &[u8; 4], chunk_data: &[u8]) -> Result<
 }
 
 /// Remove ICC profile from WebP data.
+#[cfg(feature = "icc")]
 pub fn remove_icc(webp_data: &[u8]) -> Result<Vec<u8>> {
     remove_chunk(webp_data, b"ICCP")
 }
 
 /// Remove EXIF metadata from WebP data.
+#[cfg(feature = "icc")]
 pub fn remove_exif(webp_data: &[u8]) -> Result<Vec<u8>> {
     remove_chunk(webp_data, b"EXIF")
 }
 
 /// Remove XMP metadata from WebP data.
+#[cfg(feature = "icc")]
 pub fn remove_xmp(webp_data: &[u8]) -> Result<Vec<u8>> {
     remove_chunk(webp_data, b"XMP ")
 }
 
 /// Remove a metadata chunk from WebP data.
+#[cfg(feature = "icc")]
 fn remove_chunk(webp_data: &[u8], fourcc: &[u8; 4]) -> Result<Vec<u8>> {
     let mux = unsafe { create_mux_from_data(webp_data, true) };
 
@@ -242,6 +270,818 @@ fn remove_chunk(webp_data: &[u8], fourcc: &[u8; 4]) -> Result<Vec<u8>> {
     Ok(result)
 }
 
+/// Set the loop count of an animated WebP (0 = infinite).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// let play_once = webpx::set_loop_count(webp_data, 1)?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "animation")]
+pub fn set_loop_count(webp_data: &[u8], loop_count: u32) -> Result<Vec<u8>> {
+    if loop_count > MAX_LOOP_COUNT {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "loop count {} exceeds {}",
+            loop_count,
+            MAX_LOOP_COUNT
+        ))));
+    }
+    let mut mux = Mux::from_data(webp_data)?;
+    let (bgcolor, _) = mux.animation_params()?;
+    mux.set_animation_params(bgcolor, loop_count)?;
+    mux.assemble()
+}
+
+/// Set the background color of an animated WebP (ARGB).
+#[cfg(feature = "animation")]
+pub fn set_bgcolor(webp_data: &[u8], bgcolor: u32) -> Result<Vec<u8>> {
+    let mut mux = Mux::from_data(webp_data)?;
+    let (_, loop_count) = mux.animation_params()?;
+    mux.set_animation_params(bgcolor, loop_count)?;
+    mux.assemble()
+}
+
+/// Set the duration of one frame (0-based `index`) of an animated WebP.
+#[cfg(feature = "animation")]
+pub fn set_frame_duration(webp_data: &[u8], index: u32, duration_ms: u32) -> Result<Vec<u8>> {
+    check_duration(duration_ms)?;
+    let mut found = false;
+    let result = edit_durations(webp_data, |i, duration| {
+        if i == index {
+            found = true;
+            duration_ms
+        } else {
+            duration
+        }
+    })?;
+    if !found {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "frame index {} out of range",
+            index
+        ))));
+    }
+    Ok(result)
+}
+
+/// Multiply every frame duration of an animated WebP by `factor`.
+///
+/// A factor of `0.5` plays the animation twice as fast. Scaled durations
+/// are rounded, and frames with a non-zero duration keep at least 1ms.
+#[cfg(feature = "animation")]
+pub fn scale_durations(webp_data: &[u8], factor: f32) -> Result<Vec<u8>> {
+    if !factor.is_finite() || factor <= 0.0 {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "duration scale factor must be positive, got {}",
+            factor
+        ))));
+    }
+    edit_durations(webp_data, |_, duration| {
+        if duration == 0 {
+            return 0;
+        }
+        let scaled = (duration as f64 * factor as f64 + 0.5) as u64;
+        scaled.clamp(1, MAX_DURATION as u64) as u32
+    })
+}
+
+/// Keep only the frames in `frames` (0-based) of an animated WebP.
+///
+/// If the first kept frame is drawn on top of earlier frames, it is
+/// composited and re-encoded losslessly as a full-canvas frame. If the
+/// original disposes it to the background, the frames after it are treated
+/// the same way until one no longer depends on the canvas. A single
+/// remaining full-canvas frame is written as a still image.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let webp_data: &[u8] = &[0u8; 100]; // placeholder
+/// let middle = webpx::trim_animation(webp_data, 10..20)?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "animation")]
+pub fn trim_animation(webp_data: &[u8], frames: impl RangeBounds<u32>) -> Result<Vec<u8>> {
+    let detached = detach_frames(webp_data, frames, true, None)?;
+
+    let mut mux = Mux::from_data(webp_data)?;
+    mux.clear_frames()?;
+    for frame in &detached.frames {
+        mux.push_frame(frame)?;
+    }
+    mux.assemble()
+}
+
+/// Join animated WebPs of the same canvas size into one animation.
+///
+/// A still image, such as a single-frame animation that libwebp wrote as
+/// one, is joined as a single frame shown for 100ms. The loop count and
+/// background color come from the first animated input, and metadata from
+/// the first input. The first frame of each following animation is made
+/// independent of the frames before it (see [`trim_animation`]); a
+/// full-canvas first frame only needs its blend mode changed, so it is not
+/// re-encoded.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let intro: &[u8] = &[0u8; 100]; // placeholder
+/// let body: &[u8] = &[0u8; 100]; // placeholder
+/// let joined = webpx::concat_animations(&[intro, body])?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "animation")]
+pub fn concat_animations(animations: &[&[u8]]) -> Result<Vec<u8>> {
+    let (first, rest) = animations
+        .split_first()
+        .ok_or_else(|| at!(Error::InvalidInput("no animations to concatenate".into())))?;
+
+    let head = concat_input(first, true)?;
+    let mut mux = Mux::from_data(first)?;
+    if mux.animation_params().is_err() {
+        let (bgcolor, loop_count) = rest
+            .iter()
+            .find_map(|webp_data| Mux::from_data(webp_data).ok()?.animation_params().ok())
+            .unwrap_or((0xffff_ffff, 0));
+        mux.set_animation_params(bgcolor, loop_count)?;
+    }
+    mux.clear_frames()?;
+    for frame in &head.frames {
+        mux.push_frame(frame)?;
+    }
+
+    for (i, webp_data) in rest.iter().enumerate() {
+        let detached = concat_input(webp_data, false)?;
+        if detached.canvas != head.canvas {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "animation {} is {}x{}, expected {}x{}",
+                i + 1,
+                detached.canvas.0,
+                detached.canvas.1,
+                head.canvas.0,
+                head.canvas.1
+            ))));
+        }
+        for frame in &detached.frames {
+            mux.push_frame(frame)?;
+        }
+    }
+    mux.assemble()
+}
+
+/// Frames of one input to [`concat_animations`], which may be a still image.
+#[cfg(feature = "animation")]
+fn concat_input(webp_data: &[u8], at_start: bool) -> Result<DetachedFrames> {
+    let mux = Mux::from_data(webp_data)?;
+    if mux.frame_count()? > 0 {
+        return detach_frames(webp_data, .., at_start, None);
+    }
+    let info = ImageInfo::from_webp(webp_data)?;
+    let frame = MuxFrame {
+        bitstream: mux.frame(0)?.bitstream,
+        x_offset: 0,
+        y_offset: 0,
+        duration_ms: 100,
+        blend: BlendMethod::NoBlend,
+        dispose: DisposeMethod::None,
+    };
+    Ok(DetachedFrames {
+        canvas: (info.width, info.height),
+        frames: alloc::vec![frame],
+    })
+}
+
+/// Merge runs of pixel-identical consecutive frames of an animated WebP.
+///
+/// A frame is dropped when it leaves the canvas exactly as the frame before
+/// it did, both as displayed and after disposal, and the frame after it is
+/// blended the same either way. Its duration is added to the frame kept
+/// before it. A single remaining full-canvas frame is
+/// written as a still image.
+///
+/// [`AnimationEncoder`](crate::AnimationEncoder) already merges a frame
+/// identical to the previous one while encoding; this is for existing
+/// files, or animations assembled from separately encoded pieces.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let recording: &[u8] = &[0u8; 100]; // placeholder
+/// let smaller = webpx::dedup_frames(recording)?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "animation")]
+pub fn dedup_frames(webp_data: &[u8]) -> Result<Vec<u8>> {
+    let mut mux = animated_mux(webp_data)?;
+
+    let iter = FrameIter::new(webp_data)?;
+    let (canvas_width, canvas_height) = (iter.canvas_width(), iter.canvas_height());
+    let stride = canvas_width as usize * 4;
+    let metas = scan_frames(iter, webp_data);
+    let dispose = |canvas: &mut [u8], meta: &FrameMeta| {
+        if meta.dispose == DisposeMethod::Background {
+            for y in meta.y_offset..meta.y_offset + meta.height {
+                let start = y as usize * stride + meta.x_offset as usize * 4;
+                canvas[start..start + meta.width as usize * 4].fill(0);
+            }
+        }
+    };
+    // Whether disposing `meta` on `canvas` leaves exactly `disposed`
+    let disposes_to = |canvas: &[u8], meta: &FrameMeta, disposed: &[u8]| {
+        if meta.dispose != DisposeMethod::Background {
+            return canvas == disposed;
+        }
+        let (y_range, left) = (meta.y_offset..meta.y_offset + meta.height, meta.x_offset);
+        let (left, right) = (left as usize * 4, (left + meta.width) as usize * 4);
+        let rows = canvas
+            .chunks_exact(stride)
+            .zip(disposed.chunks_exact(stride));
+        rows.enumerate().all(|(y, (row, kept))| {
+            if !y_range.contains(&(y as u32)) {
+                return row == kept;
+            }
+            row[..left] == kept[..left]
+                && row[right..] == kept[right..]
+                && kept[left..right].iter().all(|&b| b == 0)
+        })
+    };
+
+    // Whether dropping the frame before `index` can change how it renders.
+    // libwebp blends a frame against the previous one except inside the
+    // rectangle that frame cleared, and blending over a transparent pixel
+    // rounds differently than not blending, so the frame that follows a
+    // dropped one must not blend or must be a keyframe on its own.
+    let follows_freely = |index: usize| {
+        metas.get(index).map_or(true, |next| {
+            next.blend == BlendMethod::NoBlend
+                || (!next.has_alpha && next.is_full_canvas(canvas_width, canvas_height))
+        })
+    };
+
+    let mut decoder = AnimationDecoder::new(webp_data)?;
+    let mut frames: Vec<MuxFrame> = Vec::with_capacity(metas.len());
+    // Canvas after the last kept frame, as displayed and after disposal
+    let mut kept_canvas = Vec::new();
+    let mut kept_disposed = Vec::new();
+    let mut kept_meta: Option<&FrameMeta> = None;
+    for (index, meta) in metas.iter().enumerate() {
+        let canvas = decoder
+            .next_frame_ref()?
+            .ok_or_else(|| at!(Error::InvalidWebP))?
+            .data;
+        if let (Some(last), Some(kept)) = (frames.last_mut(), kept_meta) {
+            let duration_ms = last.duration_ms.saturating_add(meta.duration_ms);
+            // The next frame sees the kept frame's disposal instead
+            let same_disposal = meta.dispose == kept.dispose
+                && (meta.x_offset, meta.y_offset, meta.width, meta.height)
+                    == (kept.x_offset, kept.y_offset, kept.width, kept.height);
+            if duration_ms <= MAX_DURATION
+                && (same_disposal || follows_freely(index + 1))
+                && canvas == kept_canvas.as_slice()
+                && disposes_to(canvas, meta, &kept_disposed)
+            {
+                last.duration_ms = duration_ms;
+                continue;
+            }
+        }
+        kept_meta = Some(meta);
+        kept_canvas.clear();
+        kept_canvas.extend_from_slice(canvas);
+        kept_disposed.clear();
+        kept_disposed.extend_from_slice(canvas);
+        dispose(&mut kept_disposed, meta);
+        frames.push(mux.frame(index as u32)?);
+    }
+
+    mux.clear_frames()?;
+    for frame in &frames {
+        mux.push_frame(frame)?;
+    }
+    mux.assemble()
+}
+
+/// Assemble an animated WebP from frames that are already encoded.
+///
+/// Each frame is a still WebP whose `ALPH` and `VP8 `/`VP8L` chunks are
+/// copied into the animation without decoding or re-encoding. Frames are
+/// placed on a canvas of fixed size and must fit inside it.
+///
+/// A single full-canvas frame is written as a still image.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use webpx::{AnimationBuilder, BlendMethod, DisposeMethod};
+///
+/// let background: &[u8] = &[0u8; 100]; // placeholder, 320x240
+/// let sprite: &[u8] = &[0u8; 100]; // placeholder, 32x32
+///
+/// let mut builder = AnimationBuilder::new(320, 240)?;
+/// builder.set_loop_count(1)?;
+/// builder.push_encoded(background, 100, (0, 0), BlendMethod::NoBlend, DisposeMethod::None)?;
+/// builder.push_encoded(sprite, 100, (64, 64), BlendMethod::AlphaBlend, DisposeMethod::Background)?;
+/// let webp = builder.build()?;
+/// # Ok::<(), webpx::At<webpx::Error>>(())
+/// ```
+#[cfg(feature = "animation")]
+pub struct AnimationBuilder {
+    mux: Mux,
+    width: u32,
+    height: u32,
+    bgcolor: u32,
+    loop_count: u32,
+    frame_count: u32,
+}
+
+#[cfg(feature = "animation")]
+impl AnimationBuilder {
+    /// Create a builder for an animation with a `width` x `height` canvas.
+    ///
+    /// The animation loops forever on a white background unless changed
+    /// with [`set_loop_count`](Self::set_loop_count) and
+    /// [`set_bgcolor`](Self::set_bgcolor).
+    pub fn new(width: u32, height: u32) -> Result<Self> {
+        if width == 0 || height == 0 || width > 16383 || height > 16383 {
+            return Err(at!(Error::InvalidInput("invalid dimensions".into())));
+        }
+        let mut mux = Mux::new()?;
+        mux.set_canvas_size(width, height)?;
+        Ok(Self {
+            mux,
+            width,
+            height,
+            bgcolor: 0xffff_ffff,
+            loop_count: 0,
+            frame_count: 0,
+        })
+    }
+
+    /// Set the animation loop count (0 = infinite).
+    pub fn set_loop_count(&mut self, loop_count: u32) -> Result<()> {
+        if loop_count > MAX_LOOP_COUNT {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "loop count {} exceeds {}",
+                loop_count,
+                MAX_LOOP_COUNT
+            ))));
+        }
+        self.loop_count = loop_count;
+        Ok(())
+    }
+
+    /// Set the background color (ARGB).
+    pub fn set_bgcolor(&mut self, bgcolor: u32) {
+        self.bgcolor = bgcolor;
+    }
+
+    /// Number of frames pushed so far.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Append an encoded still WebP as the next frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `webp_data` - Still WebP image holding the frame
+    /// * `duration_ms` - Frame duration in milliseconds
+    /// * `offset` - Position of the frame on the canvas; both coordinates
+    ///   must be even, as the container stores them halved
+    /// * `blend` - How the frame is drawn over the canvas
+    /// * `dispose` - What happens to the frame's area after it is shown
+    pub fn push_encoded(
+        &mut self,
+        webp_data: &[u8],
+        duration_ms: u32,
+        offset: (u32, u32),
+        blend: BlendMethod,
+        dispose: DisposeMethod,
+    ) -> Result<()> {
+        let info = ImageInfo::from_webp(webp_data)?;
+        if info.has_animation {
+            return Err(at!(Error::InvalidInput(
+                "frame must be a still WebP, not an animation".into()
+            )));
+        }
+        let (x_offset, y_offset) = offset;
+        if x_offset % 2 != 0 || y_offset % 2 != 0 {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "frame offset ({}, {}) must be even",
+                x_offset,
+                y_offset
+            ))));
+        }
+        if x_offset.saturating_add(info.width) > self.width
+            || y_offset.saturating_add(info.height) > self.height
+        {
+            return Err(at!(Error::InvalidInput(alloc::format!(
+                "{}x{} frame at ({}, {}) exceeds the {}x{} canvas",
+                info.width,
+                info.height,
+                x_offset,
+                y_offset,
+                self.width,
+                self.height
+            ))));
+        }
+
+        self.mux.push_frame(&MuxFrame {
+            bitstream: webp_data.to_vec(),
+            x_offset,
+            y_offset,
+            duration_ms,
+            blend,
+            dispose,
+        })?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Assemble the animation.
+    pub fn build(mut self) -> Result<Vec<u8>> {
+        if self.frame_count == 0 {
+            return Err(at!(Error::InvalidInput("no frames to assemble".into())));
+        }
+        self.mux
+            .set_animation_params(self.bgcolor, self.loop_count)?;
+        self.mux.assemble()
+    }
+}
+
+/// Join consecutive pieces of one animation, encoded independently.
+///
+/// Each segment is an animation, or a still image when libwebp collapsed a
+/// single-frame segment; a still is shown for the duration given with it.
+/// Frames at the start of a segment that depend on earlier frames are
+/// re-encoded as full canvases with the segment's config.
+#[cfg(all(feature = "animation", feature = "std"))]
+pub(crate) fn join_segments(
+    segments: &[(Vec<u8>, u32, EncoderConfig)],
+    bgcolor: u32,
+    loop_count: u32,
+) -> Result<Vec<u8>> {
+    let (first, _, _) = segments
+        .first()
+        .ok_or_else(|| at!(Error::InvalidInput("no segments to join".into())))?;
+    let mut mux = Mux::from_data(first)?;
+    mux.clear_frames()?;
+    mux.set_animation_params(bgcolor, loop_count)?;
+
+    for (i, (webp_data, duration_ms, config)) in segments.iter().enumerate() {
+        if Mux::from_data(webp_data)?.frame_count()? == 0 {
+            mux.push_frame(&MuxFrame {
+                bitstream: webp_data.clone(),
+                x_offset: 0,
+                y_offset: 0,
+                duration_ms: *duration_ms,
+                blend: BlendMethod::NoBlend,
+                dispose: DisposeMethod::None,
+            })?;
+            continue;
+        }
+        for frame in &detach_frames(webp_data, .., i == 0, Some(config))?.frames {
+            mux.push_frame(frame)?;
+        }
+    }
+    mux.assemble()
+}
+
+/// Replace the first frame of an animation with a full-canvas frame that
+/// is drawn without blending, keeping its duration and disposal.
+///
+/// The new frame must leave the canvas as the old one did: the old frame
+/// was a keyframe and the new one is transparent outside its rectangle.
+#[cfg(all(feature = "animation", feature = "std"))]
+pub(crate) fn replace_first_frame(webp_data: &[u8], bitstream: Vec<u8>) -> Result<Vec<u8>> {
+    let mut mux = animated_mux(webp_data)?;
+
+    let mut frames = Vec::new();
+    for index in 0..mux.frame_count()? {
+        frames.push(mux.frame(index)?);
+    }
+    if let Some(first) = frames.first_mut() {
+        first.bitstream = bitstream;
+        first.x_offset = 0;
+        first.y_offset = 0;
+        first.blend = BlendMethod::NoBlend;
+    }
+
+    mux.clear_frames()?;
+    for frame in &frames {
+        mux.push_frame(frame)?;
+    }
+    mux.assemble()
+}
+
+/// Frames cut out of an animation, ready to be pushed into a new mux.
+#[cfg(feature = "animation")]
+struct DetachedFrames {
+    canvas: (u32, u32),
+    frames: Vec<MuxFrame>,
+}
+
+/// Read frames `range` so they render as in the original when placed at the
+/// start of an animation (`at_start`) or after arbitrary other frames.
+///
+/// Frames that depend on frames outside the range are re-encoded as full
+/// canvases with `config`, or losslessly if it is `None`.
+#[cfg(feature = "animation")]
+fn detach_frames(
+    webp_data: &[u8],
+    range: impl RangeBounds<u32>,
+    at_start: bool,
+    config: Option<&EncoderConfig>,
+) -> Result<DetachedFrames> {
+    let mux = animated_mux(webp_data)?;
+
+    let iter = FrameIter::new(webp_data)?;
+    let canvas = (iter.canvas_width(), iter.canvas_height());
+    let metas = scan_frames(iter, webp_data);
+    let count = metas.len() as u32;
+
+    let start = match range.start_bound() {
+        Bound::Included(&n) => n,
+        Bound::Excluded(&n) => n.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&n) => n.saturating_add(1),
+        Bound::Excluded(&n) => n,
+        Bound::Unbounded => count,
+    };
+    if start >= end || end > count {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "frame range {}..{} is empty or out of range ({} frames)",
+            start,
+            end,
+            count
+        ))));
+    }
+
+    let lossless = EncoderConfig::new().lossless(true).exact(true);
+    let config = config.unwrap_or(&lossless);
+    let mut decoder: Option<AnimationDecoder<'_>> = None;
+    let mut frames = Vec::with_capacity((end - start) as usize);
+    let mut dependent = true;
+    for index in start..end {
+        let mut frame = mux.frame(index)?;
+        if dependent {
+            let meta = &metas[index as usize];
+            let full_canvas = meta.is_full_canvas(canvas.0, canvas.1);
+            if meta.keyframe && (at_start && index == start || full_canvas) {
+                // A keyframe is drawn on a cleared canvas, which a
+                // full-canvas frame can always get by not blending
+                if full_canvas {
+                    frame.blend = BlendMethod::NoBlend;
+                }
+                dependent = false;
+            } else {
+                let decoder = match decoder.as_mut() {
+                    Some(decoder) => decoder,
+                    None => decoder.insert(AnimationDecoder::new(webp_data)?),
+                };
+                let composited = decoder.seek_to_frame(index)?;
+                frame.bitstream = encode_canvas(&composited, config)?;
+                frame.x_offset = 0;
+                frame.y_offset = 0;
+                frame.blend = BlendMethod::NoBlend;
+                // Clearing a sub-rectangle can't be expressed on the new
+                // full-canvas frame, so the next frame must be redrawn too
+                if frame.dispose == DisposeMethod::Background && !full_canvas {
+                    frame.dispose = DisposeMethod::None;
+                } else {
+                    dependent = false;
+                }
+            }
+        }
+        frames.push(frame);
+    }
+    Ok(DetachedFrames { canvas, frames })
+}
+
+/// Largest loop count the `ANIM` chunk can hold.
+#[cfg(feature = "animation")]
+const MAX_LOOP_COUNT: u32 = (1 << 16) - 1;
+
+/// Largest frame duration the `ANMF` chunk can hold.
+#[cfg(feature = "animation")]
+const MAX_DURATION: u32 = (1 << 24) - 1;
+
+/// Parse an animated WebP, rejecting still images, which have no frame
+/// headers to work with.
+#[cfg(feature = "animation")]
+fn animated_mux(webp_data: &[u8]) -> Result<Mux> {
+    let mux = Mux::from_data(webp_data)?;
+    mux.animation_params()?;
+    Ok(mux)
+}
+
+#[cfg(feature = "animation")]
+fn check_duration(duration_ms: u32) -> Result<()> {
+    if duration_ms > MAX_DURATION {
+        return Err(at!(Error::InvalidInput(alloc::format!(
+            "frame duration {}ms exceeds {}ms",
+            duration_ms,
+            MAX_DURATION
+        ))));
+    }
+    Ok(())
+}
+
+/// Rewrite frame durations with `f(index, duration)`, keeping everything else.
+#[cfg(feature = "animation")]
+fn edit_durations(webp_data: &[u8], mut f: impl FnMut(u32, u32) -> u32) -> Result<Vec<u8>> {
+    let mut mux = animated_mux(webp_data)?;
+
+    let mut frames = Vec::new();
+    for index in 0..mux.frame_count()? {
+        let mut frame = mux.frame(index)?;
+        frame.duration_ms = f(index, frame.duration_ms);
+        frames.push(frame);
+    }
+
+    // The mux can only append frames, so re-add them all in order
+    mux.clear_frames()?;
+    for frame in &frames {
+        mux.push_frame(frame)?;
+    }
+    mux.assemble()
+}
+
+/// A frame read from or added to a [`Mux`].
+#[cfg(feature = "animation")]
+#[derive(Debug, Clone)]
+pub(crate) struct MuxFrame {
+    /// Standalone still WebP holding the frame's `ALPH` and `VP8 `/`VP8L` chunks.
+    pub(crate) bitstream: Vec<u8>,
+    pub(crate) x_offset: u32,
+    pub(crate) y_offset: u32,
+    pub(crate) duration_ms: u32,
+    pub(crate) blend: BlendMethod,
+    pub(crate) dispose: DisposeMethod,
+}
+
+/// Owned `WebPMux` handle.
+#[cfg(feature = "animation")]
+pub(crate) struct Mux {
+    mux: *mut libwebp_sys::WebPMux,
+}
+
+#[cfg(feature = "animation")]
+impl Mux {
+    /// Create an empty mux.
+    pub(crate) fn new() -> Result<Self> {
+        let mux = libwebp_sys::WebPMuxNew();
+        if mux.is_null() {
+            return Err(at!(Error::OutOfMemory));
+        }
+        Ok(Self { mux })
+    }
+
+    /// Parse WebP data, copying it into the mux.
+    pub(crate) fn from_data(webp_data: &[u8]) -> Result<Self> {
+        let mux = unsafe { create_mux_from_data(webp_data, true) };
+        if mux.is_null() {
+            return Err(at!(Error::MuxError(MuxError::BadData)));
+        }
+        Ok(Self { mux })
+    }
+
+    /// Number of animation frames (0 for still images).
+    pub(crate) fn frame_count(&self) -> Result<u32> {
+        let mut count = 0;
+        let err = unsafe {
+            libwebp_sys::WebPMuxNumChunks(
+                self.mux,
+                libwebp_sys::WebPChunkId::WEBP_CHUNK_ANMF,
+                &mut count,
+            )
+        };
+        check(err)?;
+        Ok(count.max(0) as u32)
+    }
+
+    /// Get frame `index` (0-based). Still images have a single frame.
+    ///
+    /// The frame's `ALPH` and `VP8 `/`VP8L` chunks are copied as is.
+    pub(crate) fn frame(&self, index: u32) -> Result<MuxFrame> {
+        let mut frame = MaybeUninit::<libwebp_sys::WebPMuxFrameInfo>::zeroed();
+        let err = unsafe { libwebp_sys::WebPMuxGetFrame(self.mux, index + 1, frame.as_mut_ptr()) };
+        check(err)?;
+
+        let mut frame = unsafe { frame.assume_init() };
+        let bitstream = take_data(&mut frame.bitstream)
+            .ok_or_else(|| at!(Error::MuxError(MuxError::MemoryError)))?;
+        Ok(MuxFrame {
+            bitstream,
+            x_offset: frame.x_offset.max(0) as u32,
+            y_offset: frame.y_offset.max(0) as u32,
+            duration_ms: frame.duration.max(0) as u32,
+            blend: BlendMethod::from_libwebp(frame.blend_method),
+            dispose: DisposeMethod::from_libwebp(frame.dispose_method),
+        })
+    }
+
+    /// Remove all frames, keeping the canvas size and other chunks.
+    pub(crate) fn clear_frames(&mut self) -> Result<()> {
+        loop {
+            let err = unsafe { libwebp_sys::WebPMuxDeleteFrame(self.mux, 1) };
+            if err == libwebp_sys::WebPMuxError::WEBP_MUX_NOT_FOUND {
+                return Ok(());
+            }
+            check(err)?;
+        }
+    }
+
+    /// Append an animation frame.
+    pub(crate) fn push_frame(&mut self, frame: &MuxFrame) -> Result<()> {
+        check_duration(frame.duration_ms)?;
+        let info = libwebp_sys::WebPMuxFrameInfo {
+            bitstream: libwebp_sys::WebPData {
+                bytes: frame.bitstream.as_ptr(),
+                size: frame.bitstream.len(),
+            },
+            x_offset: frame.x_offset as i32,
+            y_offset: frame.y_offset as i32,
+            duration: frame.duration_ms as i32,
+            id: libwebp_sys::WebPChunkId::WEBP_CHUNK_ANMF,
+            dispose_method: frame.dispose.to_libwebp(),
+            blend_method: frame.blend.to_libwebp(),
+            pad: [0],
+        };
+        let err = unsafe { libwebp_sys::WebPMuxPushFrame(self.mux, &info, 1) };
+        check(err)
+    }
+
+    /// Background color and loop count. Fails for still images.
+    pub(crate) fn animation_params(&self) -> Result<(u32, u32)> {
+        let mut params = MaybeUninit::<libwebp_sys::WebPMuxAnimParams>::zeroed();
+        let err = unsafe { libwebp_sys::WebPMuxGetAnimationParams(self.mux, params.as_mut_ptr()) };
+        if err == libwebp_sys::WebPMuxError::WEBP_MUX_NOT_FOUND {
+            return Err(at!(Error::InvalidInput("not an animated WebP".into())));
+        }
+        check(err)?;
+        let params = unsafe { params.assume_init() };
+        Ok((params.bgcolor, params.loop_count.max(0) as u32))
+    }
+
+    pub(crate) fn set_animation_params(&mut self, bgcolor: u32, loop_count: u32) -> Result<()> {
+        let params = libwebp_sys::WebPMuxAnimParams {
+            bgcolor,
+            loop_count: loop_count as i32,
+        };
+        let err = unsafe { libwebp_sys::WebPMuxSetAnimationParams(self.mux, &params) };
+        check(err)
+    }
+
+    pub(crate) fn set_canvas_size(&mut self, width: u32, height: u32) -> Result<()> {
+        let err =
+            unsafe { libwebp_sys::WebPMuxSetCanvasSize(self.mux, width as i32, height as i32) };
+        check(err)
+    }
+
+    /// Assemble the mux into a WebP file.
+    pub(crate) fn assemble(&self) -> Result<Vec<u8>> {
+        let mut output = libwebp_sys::WebPData::default();
+        let err = unsafe { libwebp_sys::WebPMuxAssemble(self.mux, &mut output) };
+        if err != libwebp_sys::WebPMuxError::WEBP_MUX_OK {
+            unsafe { libwebp_sys::WebPDataClear(&mut output) };
+            return Err(at!(Error::MuxError(MuxError::from(err as i32))));
+        }
+        take_data(&mut output).ok_or_else(|| at!(Error::MuxError(MuxError::MemoryError)))
+    }
+}
+
+#[cfg(feature = "animation")]
+impl Drop for Mux {
+    fn drop(&mut self) {
+        unsafe { libwebp_sys::WebPMuxDelete(self.mux) };
+    }
+}
+
+/// Map a mux status to a result.
+#[cfg(feature = "animation")]
+fn check(err: libwebp_sys::WebPMuxError) -> Result<()> {
+    if err == libwebp_sys::WebPMuxError::WEBP_MUX_OK {
+        Ok(())
+    } else {
+        Err(at!(Error::MuxError(MuxError::from(err as i32))))
+    }
+}
+
+/// Copy out and free data allocated by libwebp. Returns `None` if empty.
+#[cfg(feature = "animation")]
+fn take_data(data: &mut libwebp_sys::WebPData) -> Option<Vec<u8>> {
+    let result = if data.bytes.is_null() || data.size == 0 {
+        None
+    } else {
+        Some(unsafe { core::slice::from_raw_parts(data.bytes, data.size) }.to_vec())
+    };
+    unsafe { libwebp_sys::WebPDataClear(data) };
+    result
+}
+
 #[cfg(test)]
 mod tests {
     // Tests would require actual WebP test data
@@ -71,6 +71,8 @@ impl StreamingDecoder {
         let csp_mode = match color_mode {
             ColorMode::Rgba => libwebp_sys::WEBP_CSP_MODE::MODE_RGBA,
             ColorMode::Bgra => libwebp_sys::WEBP_CSP_MODE::MODE_BGRA,
+            ColorMode::RgbaPremultiplied => libwebp_sys::WEBP_CSP_MODE::MODE_rgbA,
+            ColorMode::BgraPremultiplied => libwebp_sys::WEBP_CSP_MODE::MODE_bgrA,
             ColorMode::Argb => libwebp_sys::WEBP_CSP_MODE::MODE_ARGB,
             ColorMode::Rgb => libwebp_sys::WEBP_CSP_MODE::MODE_RGB,
             ColorMode::Bgr => libwebp_sys::WEBP_CSP_MODE::MODE_BGR,
@@ -115,6 +117,8 @@ impl StreamingDecoder {
         let csp_mode = match color_mode {
             ColorMode::Rgba => libwebp_sys::WEBP_CSP_MODE::MODE_RGBA,
             ColorMode::Bgra => libwebp_sys::WEBP_CSP_MODE::MODE_BGRA,
+            ColorMode::RgbaPremultiplied => libwebp_sys::WEBP_CSP_MODE::MODE_rgbA,
+            ColorMode::BgraPremultiplied => libwebp_sys::WEBP_CSP_MODE::MODE_bgrA,
             ColorMode::Argb => libwebp_sys::WEBP_CSP_MODE::MODE_ARGB,
             ColorMode::Rgb => libwebp_sys::WEBP_CSP_MODE::MODE_RGB,
             ColorMode::Bgr => libwebp_sys::WEBP_CSP_MODE::MODE_BGR,
@@ -282,6 +282,10 @@ pub enum ColorMode {
     Rgba,
     /// BGRA (8 bits per channel, 32 bits per pixel).
     Bgra,
+    /// RGBA with color channels premultiplied by alpha.
+    RgbaPremultiplied,
+    /// BGRA with color channels premultiplied by alpha.
+    BgraPremultiplied,
     /// ARGB (8 bits per channel, 32 bits per pixel).
     Argb,
     /// RGB (8 bits per channel, 24 bits per pixel).
@@ -289,8 +293,13 @@ pub enum ColorMode {
     /// BGR (8 bits per channel, 24 bits per pixel).
     Bgr,
     /// YUV420 (separate Y, U, V planes).
+    ///
+    /// Not supported by `AnimationDecoder`, which composites frames on an
+    /// RGBA canvas; decode to RGBA and convert instead.
     Yuv420,
     /// YUVA420 (YUV420 with alpha plane).
+    ///
+    /// Not supported by `AnimationDecoder`, like [`Yuv420`](Self::Yuv420).
     Yuva420,
 }
 
@@ -298,7 +307,11 @@ impl ColorMode {
     /// Bytes per pixel for packed formats.
     pub fn bytes_per_pixel(self) -> Option<usize> {
         match self {
-            ColorMode::Rgba | ColorMode::Bgra | ColorMode::Argb => Some(4),
+            ColorMode::Rgba
+            | ColorMode::Bgra
+            | ColorMode::RgbaPremultiplied
+            | ColorMode::BgraPremultiplied
+            | ColorMode::Argb => Some(4),
             ColorMode::Rgb | ColorMode::Bgr => Some(3),
             ColorMode::Yuv420 | ColorMode::Yuva420 => None, // Planar
         }
@@ -308,7 +321,20 @@ impl ColorMode {
     pub fn has_alpha(self) -> bool {
         matches!(
             self,
-            ColorMode::Rgba | ColorMode::Bgra | ColorMode::Argb | ColorMode::Yuva420
+            ColorMode::Rgba
+                | ColorMode::Bgra
+                | ColorMode::RgbaPremultiplied
+                | ColorMode::BgraPremultiplied
+                | ColorMode::Argb
+                | ColorMode::Yuva420
+        )
+    }
+
+    /// Whether color channels are premultiplied by alpha.
+    pub fn is_premultiplied(self) -> bool {
+        matches!(
+            self,
+            ColorMode::RgbaPremultiplied | ColorMode::BgraPremultiplied
         )
     }
 
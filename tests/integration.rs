@@ -97,6 +97,15 @@ fn generate_gradient_rgba(width: u32, height: u32) -> Vec<u8> {
     data
 }
 
+/// Assert that `result` failed with [`Error::InvalidInput`].
+#[cfg(any(feature = "animation", feature = "gif"))]
+fn expect_invalid<T>(result: Result<T>) {
+    match result.map(|_| ()) {
+        Err(ref e) if matches!(e.error(), Error::InvalidInput(_)) => {}
+        other => panic!("expected InvalidInput, got {:?}", other),
+    }
+}
+
 mod roundtrip {
     use super::*;
 
@@ -1084,6 +1093,17 @@ mod stop_tests {
         }
     }
 
+    /// Shares a [`DelayedCanceller`] with an encoder that needs a `'static` stop.
+    #[cfg(feature = "animation")]
+    struct SharedCanceller(std::sync::Arc<DelayedCanceller>);
+
+    #[cfg(feature = "animation")]
+    impl Stop for SharedCanceller {
+        fn check(&self) -> Result<(), StopReason> {
+            self.0.check()
+        }
+    }
+
     #[test]
     fn test_encode_cancelled_immediately() {
         let data = generate_gradient_rgba(32, 32);
@@ -1111,6 +1131,54 @@ mod stop_tests {
             other => panic!("expected Stopped(Cancelled), got {:?}", other),
         }
     }
+
+    #[test]
+    #[cfg(feature = "animation")]
+    fn test_animation_encode_cancelled_during_frame() {
+        use std::sync::Arc;
+        use webpx::AnimationEncoder;
+
+        let data = generate_gradient_rgba(256, 256);
+        let stopper = Arc::new(DelayedCanceller::new(2));
+        let mut encoder = AnimationEncoder::new(256, 256).expect("encoder");
+        encoder.set_stop(SharedCanceller(Arc::clone(&stopper)));
+
+        // One check before the frame, then cancel from the progress hook
+        match encoder.add_frame_rgba(&data, 0) {
+            Err(ref e) if matches!(e.error(), Error::Stopped(StopReason::Cancelled)) => {}
+            other => panic!("expected Stopped(Cancelled), got {:?}", other),
+        }
+        assert!(stopper.counter.load(Ordering::SeqCst));
+        assert!(stopper.checks.load(Ordering::SeqCst) > 2);
+    }
+
+    #[test]
+    #[cfg(feature = "animation")]
+    fn test_animation_finish_cancelled() {
+        use std::sync::Arc;
+        use webpx::AnimationEncoder;
+
+        let data = generate_gradient_rgba(32, 32);
+        let stopper = Arc::new(DelayedCanceller::new(usize::MAX));
+        let mut encoder = AnimationEncoder::new(32, 32).expect("encoder");
+        encoder.set_stop(ImmediateCanceller);
+        match encoder.add_frame_rgba(&data, 0) {
+            Err(ref e) if matches!(e.error(), Error::Stopped(StopReason::Cancelled)) => {}
+            other => panic!("expected Stopped(Cancelled), got {:?}", other),
+        }
+
+        let mut encoder = AnimationEncoder::new(32, 32).expect("encoder");
+        encoder.set_stop(SharedCanceller(Arc::clone(&stopper)));
+        encoder.add_frame_rgba(&data, 0).expect("add frame");
+        encoder.set_stop(ImmediateCanceller);
+        match encoder.finish(100) {
+            Err(ref e) if matches!(e.error(), Error::Stopped(StopReason::Cancelled)) => {}
+            other => panic!(
+                "expected Stopped(Cancelled), got {:?}",
+                other.map(|v| v.len())
+            ),
+        }
+    }
 }
 
 #[cfg(feature = "icc")]
@@ -1384,6 +1452,8 @@ mod types_tests {
         assert_eq!(ColorMode::Rgba.bytes_per_pixel(), Some(4));
         assert_eq!(ColorMode::Bgra.bytes_per_pixel(), Some(4));
         assert_eq!(ColorMode::Argb.bytes_per_pixel(), Some(4));
+        assert_eq!(ColorMode::RgbaPremultiplied.bytes_per_pixel(), Some(4));
+        assert_eq!(ColorMode::BgraPremultiplied.bytes_per_pixel(), Some(4));
         assert_eq!(ColorMode::Rgb.bytes_per_pixel(), Some(3));
         assert_eq!(ColorMode::Bgr.bytes_per_pixel(), Some(3));
         assert_eq!(ColorMode::Yuv420.bytes_per_pixel(), None);
@@ -1395,6 +1465,8 @@ mod types_tests {
         assert!(ColorMode::Rgba.has_alpha());
         assert!(ColorMode::Bgra.has_alpha());
         assert!(ColorMode::Argb.has_alpha());
+        assert!(ColorMode::RgbaPremultiplied.has_alpha());
+        assert!(ColorMode::BgraPremultiplied.has_alpha());
         assert!(ColorMode::Yuva420.has_alpha());
         assert!(!ColorMode::Rgb.has_alpha());
         assert!(!ColorMode::Bgr.has_alpha());
@@ -1410,6 +1482,14 @@ mod types_tests {
         assert!(!ColorMode::Bgra.is_yuv());
     }
 
+    #[test]
+    fn test_color_mode_is_premultiplied() {
+        assert!(ColorMode::RgbaPremultiplied.is_premultiplied());
+        assert!(ColorMode::BgraPremultiplied.is_premultiplied());
+        assert!(!ColorMode::Rgba.is_premultiplied());
+        assert!(!ColorMode::Bgra.is_premultiplied());
+    }
+
     #[test]
     fn test_color_mode_default() {
         assert_eq!(ColorMode::default(), ColorMode::Rgba);
@@ -2273,171 +2353,2218 @@ mod animation_tests {
     }
 
     #[test]
-    fn test_animation_add_frame_rgb() {
-        use webpx::AnimationEncoder;
+    fn test_animation_encoder_options() {
+        use webpx::{AnimationDecoder, AnimationEncoder, AnimationEncoderOptions};
 
         let width = 16;
         let height = 16;
-        let frame_rgb = generate_rgb(width, height, 100, 150, 200);
+        let frames: Vec<Vec<u8>> = (0..4)
+            .map(|i| generate_rgba(width, height, i * 60, 100, 200 - i * 40, 255))
+            .collect();
 
-        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
-        encoder.add_frame_rgb(&frame_rgb, 0).expect("add frame rgb");
-        encoder
-            .add_frame_rgb(&frame_rgb, 100)
-            .expect("add frame rgb 2");
+        let options = AnimationEncoderOptions::new()
+            .loop_count(5)
+            .bgcolor(0x11223344)
+            .kmin(1)
+            .kmax(2)
+            .allow_mixed(false)
+            .minimize_size(true)
+            .verbose(false);
+        let mut encoder = AnimationEncoder::from_options(width, height, &options).expect("encoder");
+        encoder.set_lossless(true);
+        for (i, frame) in frames.iter().enumerate() {
+            encoder
+                .add_frame_rgba(frame, i as i32 * 100)
+                .expect("add frame");
+        }
+        let webp = encoder.finish(400).expect("finish");
 
-        let webp = encoder.finish(200).expect("finish");
-        let info = ImageInfo::from_webp(&webp).expect("info");
-        // Animation with 2+ frames should be marked animated
-        // (single frame might not be)
-        assert_eq!(info.width, width);
-        assert_eq!(info.height, height);
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        let info = decoder.info();
+        assert_eq!(info.loop_count, 5);
+        assert_eq!(info.bgcolor, 0x11223344);
+        assert_eq!(info.frame_count, 4);
+
+        let decoded = decoder.decode_all().expect("decode_all");
+        for (frame, expected) in decoded.iter().zip(&frames) {
+            assert_eq!(&frame.data, expected);
+        }
     }
 
     #[test]
-    fn test_animation_decode_all_with_durations() {
-        use webpx::{AnimationDecoder, AnimationEncoder};
+    fn test_animation_encoder_options_invalid_dimensions() {
+        use webpx::{AnimationEncoder, AnimationEncoderOptions};
 
-        let width = 8;
-        let height = 8;
-        let frame1 = generate_rgba(width, height, 255, 0, 0, 255);
-        let frame2 = generate_rgba(width, height, 0, 255, 0, 255);
-        let frame3 = generate_rgba(width, height, 0, 0, 255, 255);
+        let options = AnimationEncoderOptions::default();
+        assert!(AnimationEncoder::from_options(0, 16, &options).is_err());
+        assert!(AnimationEncoder::from_options(16, 20000, &options).is_err());
+    }
 
-        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
-        encoder.add_frame_rgba(&frame1, 0).expect("add 1");
-        encoder.add_frame_rgba(&frame2, 100).expect("add 2");
-        encoder.add_frame_rgba(&frame3, 200).expect("add 3");
-        let webp = encoder.finish(300).expect("finish");
+    #[test]
+    fn test_animation_with_config() {
+        use webpx::{AnimationDecoder, AnimationEncoder, EncoderConfig};
+
+        let width = 16;
+        let height = 16;
+        let frame1 = generate_rgba(width, height, 10, 20, 30, 0);
+        let frame2 = generate_rgba(width, height, 200, 100, 50, 128);
+
+        let config = EncoderConfig::new_lossless().method(6).exact(true);
+        let mut encoder = AnimationEncoder::with_config(width, height, config).expect("encoder");
+        assert!(encoder.config().is_lossless());
+        assert_eq!(encoder.config().get_method(), 6);
+
+        encoder.add_frame_rgba(&frame1, 0).expect("add frame 1");
+        encoder.add_frame_rgba(&frame2, 100).expect("add frame 2");
+        let webp = encoder.finish(200).expect("finish");
 
+        // exact=true keeps RGB under fully transparent pixels
         let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
         let frames = decoder.decode_all().expect("decode_all");
+        assert_eq!(frames[0].data, frame1);
+    }
 
-        assert_eq!(frames.len(), 3);
-        // Timestamps are reported as END times by libwebp
-        // Frame 1 ends at 0+duration, etc.
-        assert!(frames[0].timestamp_ms >= 0);
-        assert!(frames[1].timestamp_ms > frames[0].timestamp_ms);
-        assert!(frames[2].timestamp_ms > frames[1].timestamp_ms);
+    #[test]
+    fn test_animation_with_invalid_config() {
+        use webpx::{AnimationEncoder, EncoderConfig, Error};
+
+        // libwebp only accepts thread_level 0 or 1
+        let config = EncoderConfig::new().thread_level(2);
+        let result = AnimationEncoder::with_config(16, 16, config.clone());
+        match result {
+            Err(ref e) if matches!(e.error(), Error::InvalidConfig(_)) => {}
+            other => panic!("expected InvalidConfig, got {:?}", other.err()),
+        }
+
+        let mut encoder = AnimationEncoder::new(16, 16).expect("encoder");
+        encoder.set_quality(42.0);
+        assert!(encoder.set_config(config).is_err());
+        assert_eq!(encoder.config().get_quality(), 42.0);
     }
 
     #[test]
-    fn test_animation_has_more_frames() {
-        use webpx::{AnimationDecoder, AnimationEncoder};
+    fn test_animation_add_frame_with_config() {
+        use rgb::RGBA8;
+        use webpx::{AnimationDecoder, AnimationEncoder, EncoderConfig};
 
-        let width = 8;
-        let height = 8;
-        let frame = generate_rgba(width, height, 100, 100, 100, 255);
+        let width = 16;
+        let height = 16;
+        let screenshot: Vec<RGBA8> = (0..width * height)
+            .map(|i| RGBA8::new((i % 7) as u8 * 30, (i % 5) as u8 * 50, 90, 255))
+            .collect();
+        let photo: Vec<RGBA8> = (0..width * height)
+            .map(|i| RGBA8::new(200, (i % 13) as u8 * 10, 40, 255))
+            .collect();
 
-        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
-        encoder.add_frame_rgba(&frame, 0).expect("add");
-        let webp = encoder.finish(100).expect("finish");
+        let options = AnimationEncoderOptions::new().allow_mixed(false);
+        let mut encoder = AnimationEncoder::from_options(width, height, &options).expect("encoder");
+        encoder.set_quality(50.0);
+        encoder
+            .add_frame_with_config(&screenshot, 0, &EncoderConfig::new_lossless())
+            .expect("add lossless frame");
+        encoder
+            .add_frame_with_config(&photo, 100, &EncoderConfig::new().quality(30.0))
+            .expect("add lossy frame");
+        // Per-frame overrides leave the encoder-wide config untouched
+        assert!(!encoder.config().is_lossless());
+        assert_eq!(encoder.config().get_quality(), 50.0);
+        let webp = encoder.finish(200).expect("finish");
 
         let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
-        assert!(decoder.has_more_frames());
-
-        decoder.next_frame().expect("next").expect("frame");
-        assert!(!decoder.has_more_frames());
+        let frames = decoder.decode_all().expect("decode_all");
+        assert_eq!(frames.len(), 2);
+        // Only the lossless frame round-trips exactly
+        let screenshot_bytes: Vec<u8> = screenshot
+            .iter()
+            .flat_map(|p| [p.r, p.g, p.b, p.a])
+            .collect();
+        assert_eq!(frames[0].data, screenshot_bytes);
     }
 
     #[test]
-    fn test_animation_color_mode_rgba() {
-        use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
+    fn test_raw_frames_sub_rect() {
+        use webpx::{AnimationEncoder, BitstreamFormat, BlendMethod, FrameIter};
 
-        let width = 8;
-        let height = 8;
-        let frame1 = generate_rgba(width, height, 100, 150, 200, 255);
-        let frame2 = generate_rgba(width, height, 200, 150, 100, 255);
+        let width = 32;
+        let height = 32;
+        let frame1 = generate_rgba(width, height, 50, 100, 150, 255);
+        // Second frame only changes an 8x8 block
+        let mut frame2 = frame1.clone();
+        for y in 8..16 {
+            for x in 8..16 {
+                let i = ((y * width + x) * 4) as usize;
+                frame2[i..i + 4].copy_from_slice(&[255, 0, 0, 255]);
+            }
+        }
 
         let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
-        encoder.add_frame_rgba(&frame1, 0).expect("add");
-        encoder.add_frame_rgba(&frame2, 100).expect("add");
-        let webp = encoder.finish(200).expect("finish");
+        encoder.set_lossless(true);
+        encoder.add_frame_rgba(&frame1, 0).expect("add 1");
+        encoder.add_frame_rgba(&frame2, 100).expect("add 2");
+        let webp = encoder.finish(250).expect("finish");
+
+        let iter = FrameIter::new(&webp).expect("frame iter");
+        assert_eq!(iter.canvas_width(), width);
+        assert_eq!(iter.canvas_height(), height);
+        assert_eq!(iter.len(), 2);
+
+        let frames: Vec<_> = iter.collect();
+        assert_eq!(frames[0].index, 0);
+        assert!(frames[0].is_full_canvas(width, height));
+        assert_eq!(frames[0].duration_ms, 100);
+        assert_eq!(frames[1].index, 1);
+        assert_eq!(frames[1].duration_ms, 150);
+
+        // The second frame is a sub-rectangle covering the changed block
+        let f = &frames[1];
+        assert!(!f.is_full_canvas(width, height));
+        assert!(f.x_offset <= 8 && f.y_offset <= 8);
+        assert!(f.x_offset + f.width >= 16 && f.y_offset + f.height >= 16);
+        assert!(f.width < width || f.height < height);
+
+        for frame in &frames {
+            assert_eq!(frame.format, BitstreamFormat::Lossless);
+            assert!(matches!(
+                frame.blend,
+                BlendMethod::AlphaBlend | BlendMethod::NoBlend
+            ));
+            // The bitstream decodes on its own as a still image
+            let (_, w, h) = decode_rgba(frame.bitstream).expect("decode raw frame");
+            assert_eq!((w, h), (frame.width, frame.height));
+        }
+    }
 
-        // Test RGBA mode
-        let mut decoder =
-            AnimationDecoder::with_options(&webp, ColorMode::Rgba, true).expect("decoder");
-        let decoded = decoder.next_frame().expect("next").expect("frame");
-        assert_eq!(decoded.width, width);
-        assert_eq!(decoded.height, height);
+    #[test]
+    fn test_raw_frames_fixture() {
+        use webpx::{AnimationDecoder, BitstreamFormat, BlendMethod, DisposeMethod};
+
+        let webp = include_bytes!("fixtures/animated.webp");
+        let decoder = AnimationDecoder::new(webp).expect("decoder");
+        let info = decoder.info().clone();
+
+        let frames: Vec<_> = decoder.raw_frames().expect("raw frames").collect();
+        assert_eq!(frames.len(), info.frame_count as usize);
+        for frame in &frames {
+            assert!(frame.is_full_canvas(info.width, info.height));
+            assert_eq!(frame.duration_ms, 100);
+            assert_eq!(frame.blend, BlendMethod::AlphaBlend);
+            assert_eq!(frame.dispose, DisposeMethod::Background);
+            assert_ne!(frame.format, BitstreamFormat::Undefined);
+            assert!(!frame.bitstream.is_empty());
+        }
     }
 
     #[test]
-    fn test_animation_color_mode_bgra() {
+    fn test_raw_frames_still_image() {
+        use webpx::{BlendMethod, DisposeMethod, FrameIter};
+
+        let rgba = generate_rgba(8, 8, 1, 2, 3, 255);
+        let webp = encode_rgba(&rgba, 8, 8, 80.0, Unstoppable).expect("encode");
+
+        let frames: Vec<_> = FrameIter::new(&webp).expect("frame iter").collect();
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].is_full_canvas(8, 8));
+        assert_eq!(frames[0].format, BitstreamFormat::Lossy);
+        assert_eq!(frames[0].blend, BlendMethod::AlphaBlend);
+        assert_eq!(frames[0].dispose, DisposeMethod::None);
+
+        assert!(FrameIter::new(&[0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn test_animation_decoder_owned_input() {
+        use std::sync::Arc;
         use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
 
-        let width = 8;
-        let height = 8;
-        let frame1 = generate_rgba(width, height, 100, 150, 200, 255);
-        let frame2 = generate_rgba(width, height, 200, 150, 100, 255);
+        let width = 16;
+        let height = 16;
+        let frame1 = generate_rgba(width, height, 255, 0, 0, 255);
+        let frame2 = generate_rgba(width, height, 0, 0, 255, 255);
 
         let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
-        encoder.add_frame_rgba(&frame1, 0).expect("add");
-        encoder.add_frame_rgba(&frame2, 100).expect("add");
+        encoder.add_frame_rgba(&frame1, 0).expect("add 1");
+        encoder.add_frame_rgba(&frame2, 100).expect("add 2");
         let webp = encoder.finish(200).expect("finish");
 
-        // Test BGRA mode
-        let mut decoder =
-            AnimationDecoder::with_options(&webp, ColorMode::Bgra, false).expect("decoder");
-        let decoded = decoder.next_frame().expect("next").expect("frame");
-        assert_eq!(decoded.width, width);
-        assert_eq!(decoded.height, height);
+        let borrowed = AnimationDecoder::new(&webp)
+            .expect("borrowed decoder")
+            .decode_all()
+            .expect("decode borrowed");
+
+        // Owned Vec, moved to another thread
+        let mut from_vec = AnimationDecoder::from_owned(webp.clone()).expect("vec decoder");
+        let owned = std::thread::spawn(move || from_vec.decode_all().expect("decode vec"))
+            .join()
+            .expect("thread");
+
+        // Shared Arc<[u8]>
+        let shared: Arc<[u8]> = Arc::from(webp.as_slice());
+        let mut from_arc =
+            AnimationDecoder::from_owned_with_options(Arc::clone(&shared), ColorMode::Rgba, false)
+                .expect("arc decoder");
+        let arc_frames = from_arc.decode_all().expect("decode arc");
+
+        assert_eq!(borrowed.len(), 2);
+        for ((a, b), c) in borrowed.iter().zip(&owned).zip(&arc_frames) {
+            assert_eq!(a.data, b.data);
+            assert_eq!(a.data, c.data);
+            assert_eq!(a.timestamp_ms, c.timestamp_ms);
+        }
+
+        assert!(AnimationDecoder::from_owned(vec![0u8; 32]).is_err());
+    }
+
+    fn encode_test_animation(width: u32, height: u32, frames: u8) -> Vec<u8> {
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        for i in 0..frames {
+            let frame = generate_rgba(width, height, i.wrapping_mul(40), 100, 200 - i, 255);
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 100)
+                .expect("add frame");
+        }
+        encoder.finish(frames as i32 * 100).expect("finish")
     }
 
     #[test]
-    fn test_animation_decoder_yuv_error() {
-        use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
+    fn test_animation_decoder_limits() {
+        use webpx::{AnimationDecoder, AnimationLimits, Error};
 
-        let width = 8;
-        let height = 8;
-        let frame = generate_rgba(width, height, 100, 150, 200, 255);
+        let webp = encode_test_animation(16, 16, 4);
 
-        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
-        encoder.add_frame_rgba(&frame, 0).expect("add");
-        let webp = encoder.finish(100).expect("finish");
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        let result = decoder.set_limits(AnimationLimits::new().max_canvas_pixels(16 * 15));
+        assert!(matches!(
+            result.as_ref().map_err(|e| e.error()),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(decoder
+            .set_limits(AnimationLimits::new().max_frame_count(3))
+            .is_err());
+
+        // Within limits, but only three canvases worth of output allowed
+        decoder
+            .set_limits(
+                AnimationLimits::new()
+                    .max_canvas_pixels(16 * 16)
+                    .max_frame_count(4)
+                    .max_total_bytes(3 * 16 * 16 * 4),
+            )
+            .expect("limits");
+        for _ in 0..3 {
+            decoder.next_frame().expect("next").expect("frame");
+        }
+        assert!(decoder.next_frame().is_err());
 
-        // YUV modes not supported for animation decoder
-        let result = AnimationDecoder::with_options(&webp, ColorMode::Yuv420, true);
-        assert!(result.is_err());
+        // The byte budget restarts after a reset
+        decoder.reset();
+        assert!(decoder.next_frame().expect("next").is_some());
+        assert!(decoder.decode_all().is_err());
+
+        // Cropped and scaled frames count on top of the canvas
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        decoder
+            .set_limits(AnimationLimits::new().max_total_bytes(2 * 16 * 16 * 4))
+            .expect("limits");
+        decoder.set_crop(0, 0, 16, 8).expect("set_crop");
+        decoder.next_frame().expect("next").expect("frame");
+        assert!(decoder.next_frame().is_err());
+
+        decoder.set_scale(32, 0).expect("set_scale");
+        assert!(decoder.next_frame().is_err());
+        decoder.set_scale(8, 0).expect("set_scale");
+        decoder.next_frame().expect("next").expect("frame");
     }
 
     #[test]
-    fn test_animation_invalid_frame_size() {
-        use webpx::AnimationEncoder;
+    fn test_animation_decoder_stoppable() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use webpx::{AnimationDecoder, Error, Stop, StopReason};
 
-        let mut encoder = AnimationEncoder::new(100, 100).expect("encoder");
-        let small_frame = vec![0u8; 10];
-        let result = encoder.add_frame_rgba(&small_frame, 0);
-        assert!(result.is_err());
+        struct Flag(AtomicBool);
+        impl Stop for Flag {
+            fn check(&self) -> core::result::Result<(), StopReason> {
+                if self.0.load(Ordering::Relaxed) {
+                    Err(StopReason::Cancelled)
+                } else {
+                    Ok(())
+                }
+            }
+        }
 
-        let result_rgb = encoder.add_frame_rgb(&small_frame, 0);
-        assert!(result_rgb.is_err());
+        let webp = encode_test_animation(16, 16, 3);
+        let stop = Flag(AtomicBool::new(false));
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        assert!(decoder.next_frame_stoppable(&stop).expect("next").is_some());
+
+        stop.0.store(true, Ordering::Relaxed);
+        match decoder.next_frame_stoppable(&stop) {
+            Err(ref e) if matches!(e.error(), Error::Stopped(StopReason::Cancelled)) => {}
+            other => panic!("expected Stopped, got {:?}", other.map(|f| f.is_some())),
+        }
+        assert!(decoder.decode_all_stoppable(&stop).is_err());
+
+        stop.0.store(false, Ordering::Relaxed);
+        let frames = decoder.decode_all_stoppable(&stop).expect("decode_all");
+        assert_eq!(frames.len(), 3);
     }
 
-    #[cfg(feature = "icc")]
     #[test]
-    fn test_animation_with_icc_profile() {
-        use webpx::AnimationEncoder;
+    fn test_animation_seek_stoppable() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use webpx::{AnimationDecoder, Error, Stop, StopReason};
 
-        let width = 8;
-        let height = 8;
-        let frame = generate_rgba(width, height, 100, 150, 200, 255);
+        /// Stops after a number of checks.
+        struct Budget(AtomicU32);
+        impl Stop for Budget {
+            fn check(&self) -> core::result::Result<(), StopReason> {
+                match self
+                    .0
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(StopReason::Cancelled),
+                }
+            }
+        }
+        let expect_stopped = |result: webpx::Result<webpx::Frame>| match result {
+            Err(ref e) if matches!(e.error(), Error::Stopped(StopReason::Cancelled)) => {}
+            other => panic!("expected Stopped, got {:?}", other.map(|f| f.timestamp_ms)),
+        };
 
-        // Minimal ICC profile header (zeros are not a valid ICC but libwebp accepts them)
-        let fake_icc = vec![0u8; 128];
+        // Only frame 0 is a keyframe, so seeking to frame 9 renders all ten
+        let webp = encode_moving_block_animation(100);
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        expect_stopped(decoder.seek_to_frame_stoppable(9, Budget(AtomicU32::new(5))));
+        decoder.reset();
+        expect_stopped(decoder.seek_to_time_stoppable(475, Budget(AtomicU32::new(5))));
 
-        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
-        encoder.set_icc_profile(fake_icc.clone());
-        encoder.add_frame_rgba(&frame, 0).expect("add");
+        let expected = decoder.seek_to_frame(9).expect("seek");
+        decoder.reset();
+        let frame = decoder
+            .seek_to_time_stoppable(475, Budget(AtomicU32::new(10)))
+            .expect("seek");
+        assert_eq!(frame.data, expected.data);
+    }
+
+    /// Animation with a small moving block, so later frames are sub-rectangles.
+    fn encode_moving_block_animation(kmax: u32) -> Vec<u8> {
+        use webpx::{AnimationEncoder, AnimationEncoderOptions};
+
+        let (width, height) = (32u32, 32u32);
+        let options = AnimationEncoderOptions::new()
+            .kmin(kmax - 1)
+            .kmax(kmax)
+            .allow_mixed(false);
+        let mut encoder = AnimationEncoder::from_options(width, height, &options).expect("encoder");
+        encoder.set_lossless(true);
+        for i in 0..10u32 {
+            let mut frame = generate_rgba(width, height, 20, 40, 60, 255);
+            for y in 4..12 {
+                for x in i * 2..i * 2 + 8 {
+                    let p = ((y * width + x) * 4) as usize;
+                    frame[p..p + 4].copy_from_slice(&[200, (i * 20) as u8, 0, 255]);
+                }
+            }
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 50)
+                .expect("add frame");
+        }
+        encoder.finish(500).expect("finish")
+    }
 
-        // libwebp mux accepts arbitrary byte sequences as ICC data
-        let webp = encoder
-            .finish(100)
-            .expect("finish should succeed even with invalid ICC");
+    #[test]
+    fn test_animation_decoder_iterator() {
+        use webpx::AnimationDecoder;
 
-        // Verify the ICC data was embedded
-        let extracted = webpx::get_icc_profile(&webp).expect("should extract ICC");
-        assert_eq!(extracted, Some(fake_icc), "ICC profile should round-trip");
+        let webp = encode_moving_block_animation(4);
+        let expected = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+
+        let decoder = AnimationDecoder::new(&webp).expect("decoder");
+        assert_eq!(decoder.size_hint(), (0, Some(10)));
+        let frames: Vec<_> = decoder
+            .collect::<webpx::Result<_>>()
+            .expect("iterate frames");
+        assert_eq!(frames.len(), expected.len());
+        for (frame, expected) in frames.iter().zip(&expected) {
+            assert_eq!(frame.timestamp_ms, expected.timestamp_ms);
+            assert_eq!(frame.data, expected.data);
+        }
+    }
+
+    #[test]
+    fn test_animation_decoder_iterator_stops_on_error() {
+        use webpx::{AnimationDecoder, AnimationLimits};
+
+        let webp = encode_moving_block_animation(4);
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        decoder
+            .set_limits(AnimationLimits::new().max_total_bytes(32 * 32 * 4 * 3))
+            .expect("limits");
+        let results: Vec<_> = decoder.by_ref().collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[..3].iter().all(|r| r.is_ok()));
+        assert!(results[3].is_err());
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn test_animation_decoder_frame_ref_and_into() {
+        use webpx::{AnimationDecoder, Frame};
+
+        let webp = encode_moving_block_animation(4);
+        let expected = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        for expected in &expected {
+            let frame = decoder.next_frame_ref().expect("frame").expect("some");
+            assert_eq!(frame.data, &expected.data[..]);
+            assert_eq!(frame.timestamp_ms, expected.timestamp_ms);
+            assert_eq!(frame.duration_ms, expected.duration_ms);
+            assert_eq!(frame.to_frame().data, expected.data);
+        }
+        assert!(decoder.next_frame_ref().expect("end").is_none());
+
+        decoder.reset();
+        let mut frame = Frame::default();
+        assert!(decoder.next_frame_into(&mut frame).expect("first frame"));
+        let buffer = frame.data.as_ptr();
+        let mut count = 1;
+        while decoder.next_frame_into(&mut frame).expect("frame") {
+            // The buffer is reused, not reallocated
+            assert_eq!(frame.data.as_ptr(), buffer);
+            assert_eq!(frame.data, expected[count].data);
+            assert_eq!(frame.timestamp_ms, expected[count].timestamp_ms);
+            count += 1;
+        }
+        assert_eq!(count, expected.len());
+    }
+
+    #[test]
+    fn test_animation_seek_to_frame() {
+        use webpx::AnimationDecoder;
+
+        let webp = encode_moving_block_animation(4);
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        assert_eq!(frames.len(), 10);
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        for index in [7, 2, 9, 0, 5, 6] {
+            let frame = decoder.seek_to_frame(index).expect("seek");
+            let expected = &frames[index as usize];
+            assert_eq!(frame.data, expected.data, "frame {}", index);
+            assert_eq!(frame.timestamp_ms, expected.timestamp_ms);
+            assert_eq!(frame.duration_ms, 50);
+        }
+
+        // Sequential decoding continues after the seek target
+        let next = decoder.next_frame().expect("next").expect("frame");
+        assert_eq!(next.data, frames[7].data);
+    }
+
+    #[test]
+    fn test_animation_seek_to_time() {
+        use webpx::AnimationDecoder;
+
+        let webp = encode_moving_block_animation(3);
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        // Frame 3 is displayed from 150ms until 200ms
+        for (time, index) in [(0, 0), (149, 2), (150, 3), (199, 3), (475, 9), (10_000, 9)] {
+            let frame = decoder.seek_to_time(time).expect("seek");
+            assert_eq!(frame.data, frames[index].data, "time {}", time);
+        }
+
+        expect_invalid(decoder.seek_to_frame(10));
+    }
+
+    #[test]
+    fn test_extract_frame() {
+        use webpx::{extract_frame, AnimationDecoder, EncoderConfig, FrameIter, ImageInfo};
+
+        let webp = encode_moving_block_animation(4);
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        let config = EncoderConfig::new().lossless(true);
+
+        for raw in FrameIter::new(&webp).expect("frame iter") {
+            let still = extract_frame(&webp, raw.index, &config).expect("extract");
+            let info = ImageInfo::from_webp(&still).expect("info");
+            assert!(!info.has_animation);
+            assert_eq!((info.width, info.height), (32, 32));
+
+            let (pixels, _, _) = webpx::decode_rgba(&still).expect("decode");
+            assert_eq!(
+                pixels, frames[raw.index as usize].data,
+                "frame {}",
+                raw.index
+            );
+
+            // Full-canvas frames are copied, not re-encoded
+            if raw.is_full_canvas(32, 32) {
+                assert!(still
+                    .windows(raw.bitstream.len())
+                    .any(|w| w == raw.bitstream));
+            }
+        }
+
+        assert!(extract_frame(&webp, 10, &config).is_err());
+    }
+
+    #[test]
+    fn test_split_animation() {
+        use webpx::{split_animation, AnimationDecoder, FrameIter, ImageInfo};
+
+        let webp = encode_moving_block_animation(4);
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        let raw: Vec<_> = FrameIter::new(&webp).expect("frame iter").collect();
+
+        let stills = split_animation(&webp).expect("split");
+        assert_eq!(stills.len(), frames.len());
+        let mut start = 0;
+        for (i, (still, timing)) in stills.iter().enumerate() {
+            let info = ImageInfo::from_webp(still).expect("info");
+            assert!(!info.has_animation);
+            let (pixels, width, height) = webpx::decode_rgba(still).expect("decode");
+            assert_eq!((width, height), (32, 32));
+            assert_eq!(pixels, frames[i].data, "frame {}", i);
+
+            assert_eq!(timing.start_ms, start);
+            assert_eq!(timing.duration_ms, frames[i].duration_ms);
+            start += timing.duration_ms as i32;
+
+            // Full-canvas frames are copied, not re-encoded
+            if raw[i].is_full_canvas(32, 32) {
+                assert!(still
+                    .windows(raw[i].bitstream.len())
+                    .any(|w| w == raw[i].bitstream));
+            }
+        }
+
+        let (still, timing) = split_animation(&stills[0].0)
+            .expect("split still")
+            .remove(0);
+        assert_eq!(still, stills[0].0);
+        assert_eq!(timing.duration_ms, 0);
+        assert!(split_animation(&[0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn test_edit_animation_params() {
+        use webpx::{set_bgcolor, set_loop_count, AnimationDecoder, FrameIter};
+
+        let webp = encode_test_animation(16, 16, 3);
+        let edited = set_loop_count(&webp, 3).expect("set_loop_count");
+        let edited = set_bgcolor(&edited, 0xff00_ff00).expect("set_bgcolor");
+
+        let decoder = AnimationDecoder::new(&edited).expect("decoder");
+        assert_eq!(decoder.info().loop_count, 3);
+        assert_eq!(decoder.info().bgcolor, 0xff00_ff00);
+
+        // Frame data is untouched
+        let before: Vec<_> = FrameIter::new(&webp).expect("iter").collect();
+        let after: Vec<_> = FrameIter::new(&edited).expect("iter").collect();
+        assert_eq!(before.len(), after.len());
+        for (a, b) in before.iter().zip(&after) {
+            assert_eq!(a.bitstream, b.bitstream);
+            assert_eq!(a.duration_ms, b.duration_ms);
+        }
+
+        assert!(set_loop_count(&webp, 70_000).is_err());
+
+        let still = webpx::Encoder::new_rgba(&generate_rgba(8, 8, 1, 2, 3, 255), 8, 8)
+            .encode(webpx::Unstoppable)
+            .expect("encode");
+        expect_invalid(set_loop_count(&still, 1));
+    }
+
+    #[test]
+    fn test_edit_frame_durations() {
+        use webpx::{scale_durations, set_frame_duration, AnimationDecoder, FrameIter};
+
+        let webp = encode_test_animation(16, 16, 4);
+        let edited = set_frame_duration(&webp, 1, 250).expect("set_frame_duration");
+        let durations: Vec<_> = FrameIter::new(&edited)
+            .expect("iter")
+            .map(|f| f.duration_ms)
+            .collect();
+        assert_eq!(durations, [100, 250, 100, 100]);
+
+        let scaled = scale_durations(&edited, 0.5).expect("scale_durations");
+        let frames = AnimationDecoder::new(&scaled)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        let timestamps: Vec<_> = frames.iter().map(|f| f.timestamp_ms).collect();
+        assert_eq!(timestamps, [50, 175, 225, 275]);
+
+        // Pixels are unchanged
+        let original = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        for (a, b) in original.iter().zip(&frames) {
+            assert_eq!(a.data, b.data);
+        }
+
+        assert!(set_frame_duration(&webp, 4, 100).is_err());
+        assert!(set_frame_duration(&webp, 0, 1 << 24).is_err());
+        assert!(scale_durations(&webp, 0.0).is_err());
+        assert!(scale_durations(&webp, f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_trim_animation() {
+        use webpx::{trim_animation, AnimationDecoder, FrameIter};
+
+        // Only the first frame is a keyframe, so every cut needs a redraw
+        let webp = encode_moving_block_animation(100);
+        let original = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        let raw: Vec<_> = FrameIter::new(&webp).expect("iter").collect();
+
+        for start in 0..10u32 {
+            let trimmed = trim_animation(&webp, start..).expect("trim");
+            let frames = AnimationDecoder::new(&trimmed)
+                .expect("decoder")
+                .decode_all()
+                .expect("decode_all");
+            assert_eq!(frames.len(), 10 - start as usize);
+            for (i, frame) in frames.iter().enumerate() {
+                assert_eq!(frame.data, original[start as usize + i].data);
+            }
+            // A single remaining frame becomes a still image without timing
+            if frames.len() > 1 {
+                assert!(frames.iter().all(|f| f.duration_ms == 50));
+            }
+        }
+
+        // Frames after the cut point keep their original bitstreams
+        let trimmed = trim_animation(&webp, 3..7).expect("trim");
+        let kept: Vec<_> = FrameIter::new(&trimmed).expect("iter").collect();
+        assert_eq!(kept.len(), 4);
+        assert!(kept[0].is_full_canvas(32, 32));
+        for (frame, orig) in kept[1..].iter().zip(&raw[4..7]) {
+            assert_eq!(frame.bitstream, orig.bitstream);
+        }
+
+        for range in [5..5, 8..11] {
+            expect_invalid(trim_animation(&webp, range));
+        }
+    }
+
+    #[test]
+    fn test_concat_animations() {
+        use webpx::{
+            concat_animations, set_loop_count, AnimationDecoder, AnimationEncoder, FrameIter,
+            ImageInfo,
+        };
+
+        let intro = set_loop_count(&encode_test_animation(32, 32, 3), 2).expect("loop");
+        let body = encode_moving_block_animation(100);
+        let joined = concat_animations(&[&intro, &body]).expect("concat");
+
+        let mut decoder = AnimationDecoder::new(&joined).expect("decoder");
+        assert_eq!(decoder.info().frame_count, 13);
+        assert_eq!(decoder.info().loop_count, 2);
+
+        let expected: Vec<_> = [&intro, &body]
+            .iter()
+            .flat_map(|webp| {
+                AnimationDecoder::new(webp)
+                    .expect("decoder")
+                    .decode_all()
+                    .expect("decode_all")
+            })
+            .collect();
+        let frames = decoder.decode_all().expect("decode_all");
+        for (frame, expected) in frames.iter().zip(&expected) {
+            assert_eq!(frame.data, expected.data);
+            assert_eq!(frame.duration_ms, expected.duration_ms);
+        }
+
+        // Only the blend mode of the spliced-in keyframe changes
+        let joined_raw: Vec<_> = FrameIter::new(&joined).expect("iter").collect();
+        let body_raw: Vec<_> = FrameIter::new(&body).expect("iter").collect();
+        for (a, b) in joined_raw[3..].iter().zip(&body_raw) {
+            assert_eq!(a.bitstream, b.bitstream);
+        }
+
+        // A single-frame animation is written as a still image
+        let mut encoder = AnimationEncoder::new(32, 32).expect("encoder");
+        encoder.set_lossless(true);
+        let sticker = generate_rgba(32, 32, 0, 128, 255, 255);
+        encoder.add_frame_rgba(&sticker, 0).expect("add frame");
+        let still = encoder.finish(100).expect("finish");
+        assert!(!ImageInfo::from_webp(&still).expect("info").has_animation);
+        for (inputs, sticker_index) in [([&still[..], &body], 0), ([&body[..], &still], 10)] {
+            let joined = concat_animations(&inputs).expect("concat still");
+            let mut decoder = AnimationDecoder::new(&joined).expect("decoder");
+            assert_eq!(decoder.info().frame_count, 11);
+            assert_eq!(decoder.info().loop_count, 0);
+            let frames = decoder.decode_all().expect("decode_all");
+            assert_eq!(frames[sticker_index].data, sticker);
+            assert_eq!(frames[sticker_index].duration_ms, 100);
+        }
+
+        let small = encode_test_animation(16, 16, 2);
+        assert!(concat_animations(&[&intro, &small]).is_err());
+        assert!(concat_animations(&[]).is_err());
+    }
+
+    #[test]
+    fn test_animation_builder() {
+        use webpx::{AnimationBuilder, AnimationDecoder, BlendMethod, DisposeMethod};
+
+        let background = encode_lossless(&generate_gradient_rgba(32, 32), 32, 32, Unstoppable)
+            .expect("encode background");
+        let sprite = encode_lossless(&generate_rgba(8, 8, 255, 0, 0, 255), 8, 8, Unstoppable)
+            .expect("encode sprite");
+
+        let mut builder = AnimationBuilder::new(32, 32).expect("builder");
+        builder.set_loop_count(3).expect("loop count");
+        builder
+            .push_encoded(
+                &background,
+                100,
+                (0, 0),
+                BlendMethod::NoBlend,
+                DisposeMethod::None,
+            )
+            .expect("push background");
+        builder
+            .push_encoded(
+                &sprite,
+                50,
+                (10, 10),
+                BlendMethod::AlphaBlend,
+                DisposeMethod::Background,
+            )
+            .expect("push sprite");
+        builder
+            .push_encoded(
+                &sprite,
+                70,
+                (20, 4),
+                BlendMethod::AlphaBlend,
+                DisposeMethod::None,
+            )
+            .expect("push sprite");
+        assert_eq!(builder.frame_count(), 3);
+        let webp = builder.build().expect("build");
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        assert_eq!(decoder.info().loop_count, 3);
+        let frames = decoder.decode_all().expect("decode_all");
+        let durations: Vec<_> = frames.iter().map(|f| f.duration_ms).collect();
+        assert_eq!(durations, [100, 50, 70]);
+
+        let gradient = generate_gradient_rgba(32, 32);
+        assert_eq!(frames[0].data, gradient);
+        let pixel = |frame: &webpx::Frame, x: usize, y: usize| {
+            let i = (y * 32 + x) * 4;
+            frame.data[i..i + 4].to_vec()
+        };
+        assert_eq!(pixel(&frames[1], 12, 12), [255, 0, 0, 255]);
+        assert_eq!(pixel(&frames[1], 2, 2), gradient[(2 * 32 + 2) * 4..][..4]);
+        // The first sprite was disposed to transparent before the second
+        assert_eq!(pixel(&frames[2], 12, 12), [0, 0, 0, 0]);
+        assert_eq!(pixel(&frames[2], 22, 6), [255, 0, 0, 255]);
+
+        let mut builder = AnimationBuilder::new(32, 32).expect("builder");
+        let blend = BlendMethod::AlphaBlend;
+        let dispose = DisposeMethod::None;
+        expect_invalid(builder.push_encoded(&sprite, 50, (3, 0), blend, dispose));
+        expect_invalid(builder.push_encoded(&sprite, 50, (26, 0), blend, dispose));
+        expect_invalid(builder.push_encoded(&webp, 50, (0, 0), blend, dispose));
+        expect_invalid(builder.set_loop_count(1 << 16));
+        assert_eq!(builder.frame_count(), 0);
+        expect_invalid(builder.build());
+        assert!(AnimationBuilder::new(0, 32).is_err());
+    }
+
+    #[test]
+    fn test_dedup_frames() {
+        use webpx::{
+            dedup_frames, AnimationBuilder, AnimationDecoder, BlendMethod, DisposeMethod, FrameIter,
+        };
+
+        let background = encode_lossless(&generate_gradient_rgba(32, 32), 32, 32, Unstoppable)
+            .expect("encode background");
+        let sprite = encode_lossless(&generate_rgba(8, 8, 255, 0, 0, 255), 8, 8, Unstoppable)
+            .expect("encode sprite");
+
+        let (blend, no_blend) = (BlendMethod::AlphaBlend, BlendMethod::NoBlend);
+        let (keep, clear) = (DisposeMethod::None, DisposeMethod::Background);
+        let mut builder = AnimationBuilder::new(32, 32).expect("builder");
+        for (still, duration, offset, blend, dispose) in [
+            (&background, 100, (0, 0), no_blend, keep),
+            // Identical: merged into the frame before
+            (&background, 50, (0, 0), no_blend, keep),
+            (&sprite, 40, (10, 10), blend, keep),
+            // Looks the same, but clears the sprite afterwards: kept
+            (&sprite, 30, (10, 10), blend, clear),
+            // Redraws the sprite and clears it again: merged
+            (&sprite, 20, (10, 10), blend, clear),
+            (&background, 60, (0, 0), no_blend, keep),
+        ] {
+            builder
+                .push_encoded(still, duration, offset, blend, dispose)
+                .expect("push");
+        }
+        let webp = builder.build().expect("build");
+
+        let deduped = dedup_frames(&webp).expect("dedup");
+        let durations: Vec<_> = FrameIter::new(&deduped)
+            .expect("iter")
+            .map(|f| f.duration_ms)
+            .collect();
+        assert_eq!(durations, [150, 40, 50, 60]);
+
+        // Every original frame is still shown for its whole duration
+        let original = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        let merged = AnimationDecoder::new(&deduped)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        for frame in &original {
+            let shown = merged
+                .iter()
+                .find(|m| m.timestamp_ms >= frame.timestamp_ms)
+                .expect("covering frame");
+            assert_eq!(
+                shown.data, frame.data,
+                "frame ending at {}",
+                frame.timestamp_ms
+            );
+        }
+
+        // Kept frames are not re-encoded
+        let raw: Vec<_> = FrameIter::new(&webp).expect("iter").collect();
+        let kept: Vec<_> = FrameIter::new(&deduped).expect("iter").collect();
+        for (frame, index) in kept.iter().zip([0, 2, 3, 5]) {
+            assert_eq!(frame.bitstream, raw[index].bitstream);
+        }
+
+        // Nothing to merge
+        let plain = encode_moving_block_animation(4);
+        let unchanged = dedup_frames(&plain).expect("dedup");
+        assert_eq!(FrameIter::new(&unchanged).expect("iter").len(), 10);
+
+        expect_invalid(dedup_frames(&background));
+    }
+
+    #[test]
+    fn test_dedup_frames_keeps_blending() {
+        use webpx::{dedup_frames, AnimationBuilder, AnimationDecoder, BlendMethod, DisposeMethod};
+
+        let clear = encode_lossless(&generate_rgba(8, 8, 0, 0, 0, 0), 8, 8, Unstoppable)
+            .expect("encode clear");
+        let hole = encode_lossless(&generate_rgba(2, 4, 0, 0, 0, 0), 2, 4, Unstoppable)
+            .expect("encode hole");
+        let faint = encode_lossless(&generate_rgba(2, 4, 255, 255, 255, 3), 2, 4, Unstoppable)
+            .expect("encode faint");
+
+        // The hole changes nothing on screen, but libwebp does not blend the
+        // next frame inside the rectangle it cleared
+        let (blend, no_blend) = (BlendMethod::AlphaBlend, BlendMethod::NoBlend);
+        let (keep, background) = (DisposeMethod::None, DisposeMethod::Background);
+        let mut builder = AnimationBuilder::new(8, 8).expect("builder");
+        for (still, offset, blend, dispose) in [
+            (&clear, (0, 0), no_blend, keep),
+            (&hole, (2, 2), blend, background),
+            (&faint, (2, 2), blend, keep),
+        ] {
+            builder
+                .push_encoded(still, 100, offset, blend, dispose)
+                .expect("push");
+        }
+        let webp = builder.build().expect("build");
+
+        let deduped = dedup_frames(&webp).expect("dedup");
+        let last_frame = |webp: &[u8]| {
+            AnimationDecoder::new(webp)
+                .expect("decoder")
+                .decode_all()
+                .expect("decode_all")
+                .pop()
+                .expect("frame")
+                .data
+        };
+        let original = last_frame(&webp);
+        assert_eq!(&original[(2 * 8 + 2) * 4..][..4], &[255, 255, 255, 3]);
+        assert_eq!(last_frame(&deduped), original);
+    }
+
+    #[test]
+    fn test_animation_encoder_img_and_stride() {
+        use imgref::Img;
+        use rgb::RGBA8;
+        use webpx::{AnimationDecoder, AnimationEncoder};
+
+        // Two 8x8 frames taken from the left and right halves of a 24x8 buffer
+        let (width, height) = (8u32, 8u32);
+        let buffer: Vec<RGBA8> = (0..24 * 8)
+            .map(|i| RGBA8::new((i % 24 * 10) as u8, (i / 24 * 30) as u8, 7, 255))
+            .collect();
+        let img = Img::new(buffer.as_slice(), 24, 8);
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.set_lossless(true);
+        encoder
+            .add_frame_img(img.sub_image(0, 0, 8, 8), 0)
+            .expect("add sub-image");
+        encoder
+            .add_frame_stride(&buffer[16..], 24, 100)
+            .expect("add strided frame");
+        let webp = encoder.finish(200).expect("finish");
+
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        for (frame, left) in frames.iter().zip([0, 16]) {
+            for y in 0..8 {
+                for x in 0..8 {
+                    let px = buffer[y * 24 + left + x];
+                    let i = (y * 8 + x) * 4;
+                    assert_eq!(&frame.data[i..i + 4], &[px.r, px.g, px.b, px.a]);
+                }
+            }
+        }
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        expect_invalid(encoder.add_frame_img(img.sub_image(0, 0, 8, 4), 0));
+        expect_invalid(encoder.add_frame_stride(&buffer[..], 4, 0));
+        expect_invalid(encoder.add_frame_stride(&buffer[180..], 24, 0));
+    }
+
+    #[test]
+    fn test_animation_encoder_argb_and_yuv() {
+        use webpx::{AnimationDecoder, AnimationEncoder, YuvPlanesRef};
+
+        let (width, height) = (16u32, 16u32);
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.set_lossless(true);
+
+        // Packed ARGB, then a 16-pixel view into a 20-pixel-stride buffer
+        encoder
+            .add_frame_argb(&vec![0xff_ff_00_00; 16 * 16], 0)
+            .expect("add argb");
+        encoder
+            .add_frame_argb_stride(&vec![0xff_00_00_ff; 20 * 16], 20, 100)
+            .expect("add strided argb");
+
+        // Mid-gray YUV 4:2:0
+        let y = vec![128u8; 16 * 16];
+        let uv = vec![128u8; 8 * 8];
+        let planes = YuvPlanesRef {
+            y: &y,
+            y_stride: 16,
+            u: &uv,
+            u_stride: 8,
+            v: &uv,
+            v_stride: 8,
+            a: None,
+            a_stride: 0,
+            width,
+            height,
+        };
+        encoder.add_frame_yuv(planes, 200).expect("add yuv");
+
+        expect_invalid(encoder.add_frame_yuv(
+            YuvPlanesRef {
+                u: &uv[..10],
+                ..planes
+            },
+            300,
+        ));
+        expect_invalid(encoder.add_frame_yuv(YuvPlanesRef { width: 8, ..planes }, 300));
+        expect_invalid(encoder.add_frame_argb(&[0; 16 * 15], 300));
+
+        let webp = encoder.finish(300).expect("finish");
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        assert_eq!(frames.len(), 3);
+        assert_eq!(&frames[0].data[..4], &[255, 0, 0, 255]);
+        assert_eq!(&frames[1].data[..4], &[0, 0, 255, 255]);
+        let gray = &frames[2].data[..4];
+        assert!(
+            gray[..3].iter().all(|&c| c.abs_diff(gray[0]) <= 2),
+            "{:?}",
+            gray
+        );
+        assert!((100..160).contains(&gray[0]), "{:?}", gray);
+    }
+
+    #[test]
+    fn test_animation_encoder_progress() {
+        use std::sync::{Arc, Mutex};
+        use webpx::{AnimationEncoder, AnimationProgress};
+
+        let reports: Arc<Mutex<Vec<AnimationProgress>>> = Arc::default();
+        let mut encoder = AnimationEncoder::new(64, 64).expect("encoder");
+        let sink = Arc::clone(&reports);
+        encoder.set_progress(move |p| sink.lock().unwrap().push(p));
+        for i in 0..3 {
+            let frame = generate_rgba(64, 64, 80 * i as u8, 100, 50, 255);
+            encoder.add_frame_rgba(&frame, i * 100).expect("add frame");
+        }
+        encoder.finish(300).expect("finish");
+
+        let reports = reports.lock().unwrap();
+        let done: Vec<_> = reports
+            .iter()
+            .filter(|p| p.frame_percent == 0)
+            .map(|p| p.frames_done)
+            .collect();
+        assert_eq!(done, [1, 2, 3]);
+        assert!(reports.iter().any(|p| p.frame_percent == 100));
+        for pair in reports.windows(2) {
+            // Percent only rises within a frame
+            if pair[0].frames_done == pair[1].frames_done {
+                assert!(pair[1].frame_percent > pair[0].frame_percent, "{:?}", pair);
+            }
+            assert!(pair[1].frame_percent <= 100);
+        }
+    }
+
+    #[test]
+    fn test_animation_encoder_frame_stats() {
+        use webpx::{AnimationEncoder, AnimationEncoderOptions, BitstreamFormat};
+
+        let (width, height) = (32u32, 32u32);
+        let options = AnimationEncoderOptions::new().allow_mixed(false);
+        let mut encoder = AnimationEncoder::from_options(width, height, &options).expect("encoder");
+        encoder.set_lossless(true);
+        encoder.set_compute_psnr(true).expect("set_compute_psnr");
+        let background = generate_rgba(width, height, 20, 40, 60, 255);
+        let mut moved = background.clone();
+        for y in 4..12 {
+            let p = (y * width as usize + 8) * 4;
+            moved[p..p + 32].fill(200);
+        }
+        // The repeated frame is merged into the first one's duration
+        for (i, frame) in [&background, &background, &moved].iter().enumerate() {
+            encoder
+                .add_frame_rgba(frame, i as i32 * 100)
+                .expect("add frame");
+        }
+        let (webp, stats) = encoder.finish_with_stats(300).expect("finish");
+
+        assert_eq!(stats.len(), 2);
+        assert!(stats[0].keyframe);
+        assert_eq!((stats[0].start_ms, stats[0].duration_ms), (0, 200));
+        assert_eq!((stats[0].width, stats[0].height), (width, height));
+        assert_eq!((stats[1].start_ms, stats[1].duration_ms), (200, 100));
+        assert!(!stats[1].keyframe);
+        // Only the changed block is emitted
+        assert!(stats[1].width < width && stats[1].height < height);
+        assert!(stats[1].x_offset <= 8 && stats[1].y_offset <= 4);
+        for frame in &stats {
+            assert_eq!(frame.format, BitstreamFormat::Lossless);
+            assert!(frame.coded_size > 0 && (frame.coded_size as usize) < webp.len());
+            assert_eq!(frame.psnr, Some(99.0));
+        }
+    }
+
+    #[test]
+    fn test_animation_encoder_frame_stats_lossy() {
+        use webpx::{AnimationEncoder, AnimationEncoderOptions, BitstreamFormat};
+
+        let (width, height) = (64u32, 64u32);
+        let options = AnimationEncoderOptions::new().allow_mixed(false);
+        let mut encoder = AnimationEncoder::from_options(width, height, &options).expect("encoder");
+        encoder.set_quality(50.0);
+        for i in 0..3u8 {
+            let frame = generate_rgba(width, height, 60 * i, 100, 200 - 50 * i, 255);
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 100)
+                .expect("add frame");
+        }
+        let (_, stats) = encoder.finish_with_stats(300).expect("finish");
+        assert_eq!(stats.len(), 3);
+        for frame in &stats {
+            assert_eq!(frame.format, BitstreamFormat::Lossy);
+            // PSNR is only computed on request
+            assert_eq!(frame.psnr, None);
+        }
+
+        let mut encoder = AnimationEncoder::from_options(width, height, &options).expect("encoder");
+        encoder.set_quality(50.0);
+        encoder.set_compute_psnr(true).expect("set_compute_psnr");
+        let frame = generate_gradient_rgba(width, height);
+        encoder.add_frame_rgba(&frame, 0).expect("add frame");
+        let (_, stats) = encoder.finish_with_stats(100).expect("finish");
+        let psnr = stats[0].psnr.expect("psnr");
+        assert!(psnr > 20.0 && psnr < 99.0, "psnr {}", psnr);
+    }
+
+    #[test]
+    fn test_animation_encoder_psnr_after_first_frame() {
+        use webpx::AnimationEncoder;
+
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        let frame = generate_rgba(8, 8, 255, 0, 0, 255);
+        encoder.add_frame_rgba(&frame, 0).expect("add frame");
+        expect_invalid(encoder.set_compute_psnr(true));
+    }
+
+    #[test]
+    fn test_animation_encoder_durations() {
+        use rgb::RGBA8;
+        use webpx::{AnimationDecoder, AnimationEncoder};
+
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        for (i, duration) in [40u32, 60, 100].into_iter().enumerate() {
+            let pixels = vec![RGBA8::new(80 * i as u8, 0, 0, 255); 64];
+            encoder
+                .add_frame_with_duration(&pixels, duration)
+                .expect("add frame");
+        }
+        // A timestamped frame may follow once the previous frame has ended
+        let pixels = vec![RGBA8::new(0, 0, 255, 255); 64];
+        encoder.add_frame(&pixels, 250).expect("add frame");
+        let webp = encoder.finish(300).expect("finish");
+
+        let durations: Vec<_> = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .map(|frame| frame.expect("frame").duration_ms)
+            .collect();
+        assert_eq!(durations, [40, 60, 150, 50]);
+
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        for (color, duration) in [(255u8, 30u32), (0, 70)] {
+            let pixels = vec![RGBA8::new(color, color, color, 255); 64];
+            encoder
+                .add_frame_with_duration(&pixels, duration)
+                .expect("add frame");
+        }
+        let webp = encoder
+            .finish_after_last_frame()
+            .expect("finish after last frame");
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        assert_eq!(frames[1].duration_ms, 70);
+        assert_eq!(frames[1].timestamp_ms, 100);
+    }
+
+    #[test]
+    fn test_animation_encoder_timestamp_validation() {
+        use rgb::RGBA8;
+        use webpx::AnimationEncoder;
+
+        let pixels = vec![RGBA8::new(10, 20, 30, 255); 64];
+
+        // Timestamps must not go backwards
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        encoder.add_frame(&pixels, 100).expect("add frame");
+        expect_invalid(encoder.add_frame(&pixels, 50));
+        // The start of an un-timed frame is unknown
+        expect_invalid(encoder.add_frame_with_duration(&pixels, 10));
+        expect_invalid(encoder.finish_after_last_frame());
+
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        encoder.add_frame(&pixels, 100).expect("add frame");
+        expect_invalid(encoder.finish(99));
+
+        // A frame may not start before the previous one ends
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        encoder
+            .add_frame_with_duration(&pixels, 100)
+            .expect("add frame");
+        expect_invalid(encoder.add_frame(&pixels, 50));
+        expect_invalid(encoder.add_frame_with_duration(&pixels, u32::MAX));
+
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        encoder
+            .add_frame_with_duration(&pixels, 100)
+            .expect("add frame");
+        expect_invalid(encoder.finish(60));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_animation_encoder_parallel() {
+        use std::sync::{Arc, Mutex};
+        use webpx::{AnimationDecoder, AnimationEncoder, AnimationEncoderOptions, EncoderConfig};
+
+        let (width, height) = (24u32, 16u32);
+        // A square moving over a gradient, partly transparent in odd frames
+        let frames: Vec<Vec<u8>> = (0..13u32)
+            .map(|i| {
+                let mut frame = generate_gradient_rgba(width, height);
+                for y in 4..10 {
+                    for x in i..i + 6 {
+                        let px = ((y * width + x) * 4) as usize;
+                        let alpha = if i % 2 == 1 { 128 } else { 255 };
+                        frame[px..px + 4].copy_from_slice(&[255, 0, 0, alpha]);
+                    }
+                }
+                frame
+            })
+            .collect();
+        let options = AnimationEncoderOptions::new().allow_mixed(false);
+        let config = EncoderConfig::new().lossless(true).exact(true);
+
+        let encode = |parallel: bool| {
+            let mut encoder =
+                AnimationEncoder::from_options(width, height, &options).expect("encoder");
+            encoder.set_config(config.clone()).expect("config");
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let sink = Arc::clone(&reported);
+            encoder.set_progress(move |p| sink.lock().unwrap().push(p.frames_done));
+            if parallel {
+                // Groups of 4, 4, 4 and a single trailing frame
+                encoder.set_parallel(2, 4).expect("set_parallel");
+            }
+            for (i, frame) in frames.iter().enumerate() {
+                encoder
+                    .add_frame_rgba(frame, i as i32 * 50)
+                    .expect("add frame");
+            }
+            let webp = encoder.finish(700).expect("finish");
+            let reported = reported.lock().unwrap().clone();
+            (webp, reported)
+        };
+
+        let (sequential, _) = encode(false);
+        let (parallel, reported) = encode(true);
+        assert_eq!(reported.last(), Some(&13));
+        assert!(reported.windows(2).all(|w| w[0] < w[1]));
+
+        let expected = AnimationDecoder::new(&sequential)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode sequential");
+        let actual = AnimationDecoder::new(&parallel)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode parallel");
+        assert_eq!(actual.len(), 13);
+        assert_eq!(actual.len(), expected.len());
+        for (i, (a, e)) in actual.iter().zip(&expected).enumerate() {
+            assert_eq!(a.timestamp_ms, e.timestamp_ms, "frame {}", i);
+            assert_eq!(a.duration_ms, e.duration_ms, "frame {}", i);
+            assert!(a.data == e.data, "frame {} pixels differ", i);
+            assert!(a.data == frames[i], "frame {} differs from source", i);
+        }
+        assert_eq!(actual[12].duration_ms, 100);
+
+        // Must be enabled up front, with non-zero arguments
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        for result in [encoder.set_parallel(0, 4), encoder.set_parallel(2, 0)] {
+            expect_invalid(result);
+        }
+        encoder.add_frame_rgba(&frames[0], 0).expect("add frame");
+        expect_invalid(encoder.set_parallel(2, 4));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_animation_encoder_parallel_lossy() {
+        use webpx::{
+            AnimationDecoder, AnimationEncoder, AnimationEncoderOptions, BitstreamFormat,
+            EncoderConfig, FrameIter,
+        };
+
+        // A block moving over a transparent canvas, so each group starts
+        // with a sub-rectangle that has to be redrawn when groups are joined
+        let (width, height) = (32u32, 32u32);
+        let options = AnimationEncoderOptions::new()
+            .allow_mixed(false)
+            .loop_count(100_000);
+        let mut encoder = AnimationEncoder::from_options(width, height, &options).expect("encoder");
+        encoder
+            .set_config(EncoderConfig::new().quality(75.0))
+            .expect("config");
+        encoder.set_parallel(2, 3).expect("set_parallel");
+        for i in 0..9u32 {
+            let mut frame = generate_rgba(width, height, 0, 0, 0, 0);
+            for y in 8..16 {
+                for x in i * 2..i * 2 + 8 {
+                    let px = ((y * width + x) * 4) as usize;
+                    frame[px..px + 4].copy_from_slice(&[200, 40, 40, 255]);
+                }
+            }
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 50)
+                .expect("add frame");
+        }
+        let webp = encoder.finish(450).expect("finish");
+
+        for frame in FrameIter::new(&webp).expect("iter") {
+            assert_eq!(
+                frame.format,
+                BitstreamFormat::Lossy,
+                "frame {}",
+                frame.index
+            );
+        }
+        let decoder = AnimationDecoder::new(&webp).expect("decoder");
+        assert_eq!(decoder.info().frame_count, 9);
+        assert_eq!(decoder.info().loop_count, 65535);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_animation_encoder_parallel_boundary_quality() {
+        use webpx::{AnimationDecoder, AnimationEncoder, AnimationEncoderOptions, EncoderConfig};
+
+        // Opaque blocks moving over a transparent canvas: libwebp trims each
+        // group's first frame to a sub-rectangle
+        let (width, height) = (48u32, 32u32);
+        let frames: Vec<Vec<u8>> = (0..8u32)
+            .map(|i| {
+                let mut frame = generate_rgba(width, height, 0, 0, 0, 0);
+                for y in 6..22 {
+                    for x in i * 3..i * 3 + 20 {
+                        let px = ((y * width + x) * 4) as usize;
+                        let shade = ((x * 11 + y * 7) % 256) as u8;
+                        frame[px..px + 4].copy_from_slice(&[shade, 255 - shade, 90, 255]);
+                    }
+                }
+                frame
+            })
+            .collect();
+        // PSNR of premultiplied values, as transparent colors are arbitrary
+        let psnr = |a: &[u8], b: &[u8]| {
+            let premultiply = |px: &[u8], c: usize| px[c] as f64 * px[3] as f64 / 255.0;
+            let mut sum = 0.0;
+            for (pa, pb) in a.chunks(4).zip(b.chunks(4)) {
+                for c in 0..4 {
+                    let (va, vb) = if c == 3 {
+                        (pa[3] as f64, pb[3] as f64)
+                    } else {
+                        (premultiply(pa, c), premultiply(pb, c))
+                    };
+                    sum += (va - vb) * (va - vb);
+                }
+            }
+            let mse = sum / a.len() as f64;
+            10.0 * (255.0 * 255.0 / mse.max(1e-10)).log10()
+        };
+
+        let encode = |parallel: bool| {
+            let options = AnimationEncoderOptions::new().allow_mixed(false);
+            let mut encoder =
+                AnimationEncoder::from_options(width, height, &options).expect("encoder");
+            encoder
+                .set_config(EncoderConfig::new().quality(75.0))
+                .expect("config");
+            if parallel {
+                encoder.set_parallel(2, 4).expect("set_parallel");
+            }
+            for (i, frame) in frames.iter().enumerate() {
+                encoder
+                    .add_frame_rgba(frame, i as i32 * 50)
+                    .expect("add frame");
+            }
+            let webp = encoder.finish(400).expect("finish");
+            let decoded = AnimationDecoder::new(&webp)
+                .expect("decoder")
+                .decode_all()
+                .expect("decode_all");
+            decoded
+        };
+        let (sequential, parallel) = (encode(false), encode(true));
+        assert_eq!(parallel.len(), frames.len());
+
+        // The second group starts with a keyframe at frame 4, where the
+        // sequential encoder may also pick a smaller delta frame
+        for i in 4..8 {
+            let serial_psnr = psnr(&sequential[i].data, &frames[i]);
+            let parallel_psnr = psnr(&parallel[i].data, &frames[i]);
+            assert!(
+                parallel_psnr > serial_psnr - 0.75,
+                "frame {}: {:.2} dB parallel, {:.2} dB sequential",
+                i,
+                parallel_psnr,
+                serial_psnr
+            );
+        }
+    }
+
+    #[test]
+    fn test_animation_decoder_premultiplied() {
+        use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
+
+        let (width, height) = (16, 16);
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.set_lossless(true);
+        for (i, alpha) in [255u8, 128, 0].into_iter().enumerate() {
+            let frame = generate_rgba(width, height, 200, 100, 50, alpha);
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 100)
+                .expect("add frame");
+        }
+        let webp = encoder.finish(300).expect("finish");
+
+        let straight = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        for (mode, order) in [
+            (ColorMode::RgbaPremultiplied, [0, 1, 2]),
+            (ColorMode::BgraPremultiplied, [2, 1, 0]),
+        ] {
+            let frames = AnimationDecoder::with_options(&webp, mode, false)
+                .expect("decoder")
+                .decode_all()
+                .expect("decode_all");
+            assert_eq!(frames.len(), straight.len());
+            for (frame, reference) in frames.iter().zip(&straight) {
+                for (px, rgba) in frame.data.chunks(4).zip(reference.data.chunks(4)) {
+                    let alpha = rgba[3] as u32;
+                    assert_eq!(px[3], rgba[3]);
+                    for (c, &src) in order.iter().enumerate() {
+                        let expected = rgba[src] as u32 * alpha / 255;
+                        assert!(
+                            (px[c] as u32).abs_diff(expected) <= 1,
+                            "{:?} vs {:?}",
+                            px,
+                            rgba
+                        );
+                    }
+                }
+            }
+        }
+
+        assert!(AnimationDecoder::with_options(&webp, ColorMode::Rgb, false).is_err());
+    }
+
+    #[test]
+    fn test_animation_decoder_crop_and_scale() {
+        use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
+
+        let webp = encode_moving_block_animation(4);
+        let full = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        let crop = |data: &[u8], left: usize, top: usize, width: usize, height: usize| {
+            let mut out = Vec::new();
+            for y in top..top + height {
+                out.extend_from_slice(&data[(y * 32 + left) * 4..(y * 32 + left + width) * 4]);
+            }
+            out
+        };
+
+        // Cropping is exact
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        decoder.set_crop(3, 2, 16, 12).expect("set_crop");
+        let frames = decoder.decode_all().expect("decode_all");
+        assert_eq!(frames.len(), full.len());
+        for (frame, reference) in frames.iter().zip(&full) {
+            assert_eq!((frame.width, frame.height), (16, 12));
+            assert_eq!(frame.data, crop(&reference.data, 3, 2, 16, 12));
+            assert_eq!(frame.timestamp_ms, reference.timestamp_ms);
+        }
+
+        // Halving keeps flat areas and lands the block where expected
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        decoder.set_scale(16, 0).expect("set_scale");
+        let frame = decoder.seek_to_frame(0).expect("seek");
+        assert_eq!((frame.width, frame.height), (16, 16));
+        assert_eq!(frame.data.len(), 16 * 16 * 4);
+        assert_eq!(&frame.data[..4], &full[0].data[..4]);
+        let block = ((3 * 16 + 1) * 4) as usize;
+        assert_eq!(
+            &frame.data[block..block + 4],
+            &full[0].data[(5 * 32 + 2) * 4..][..4]
+        );
+
+        // Scaling applies to the cropped region, and frame refs see it too
+        decoder.set_crop(0, 0, 32, 16).expect("set_crop");
+        let frame = decoder.next_frame_ref().expect("next").expect("frame");
+        assert_eq!((frame.width, frame.height), (16, 8));
+        assert_eq!(frame.data.len(), 16 * 8 * 4);
+
+        // Cropping matches the full decode in every color mode, including
+        // premultiplied frames blended over translucent ones
+        let mut encoder = AnimationEncoder::new(16, 16).expect("encoder");
+        encoder.set_lossless(true);
+        for i in 0..3u8 {
+            let frame: Vec<u8> = (0..16 * 16u32)
+                .flat_map(|p| {
+                    let (x, y, i) = (p % 16, p / 16, i as u32);
+                    let alpha = if (x + i) % 4 == 0 {
+                        255
+                    } else {
+                        (x * 13 + y * 3 + i * 40) % 256
+                    };
+                    [x * 16, y * 16, 255 - i * 60, alpha].map(|c| c as u8)
+                })
+                .collect();
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 100)
+                .expect("add frame");
+        }
+        let translucent = encoder.finish(300).expect("finish");
+        for mode in [
+            ColorMode::Rgba,
+            ColorMode::Bgra,
+            ColorMode::RgbaPremultiplied,
+            ColorMode::BgraPremultiplied,
+        ] {
+            let full = AnimationDecoder::with_options(&translucent, mode, false)
+                .expect("decoder")
+                .decode_all()
+                .expect("decode_all");
+            let mut decoder =
+                AnimationDecoder::with_options(&translucent, mode, false).expect("decoder");
+            decoder.set_crop(2, 6, 9, 7).expect("set_crop");
+            let frames = decoder.decode_all().expect("decode_all");
+            assert_eq!(frames.len(), 3);
+            for (frame, reference) in frames.iter().zip(&full) {
+                let expected: Vec<u8> = reference
+                    .data
+                    .chunks(16 * 4)
+                    .skip(6)
+                    .take(7)
+                    .flat_map(|row| &row[2 * 4..11 * 4])
+                    .copied()
+                    .collect();
+                assert_eq!(frame.data, expected, "{:?}", mode);
+            }
+        }
+
+        // Premultiplication happens after scaling
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        encoder.set_lossless(true);
+        for (i, alpha) in [255u8, 128].into_iter().enumerate() {
+            let frame = generate_rgba(8, 8, 200, 100, 50, alpha);
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 100)
+                .expect("add frame");
+        }
+        let webp = encoder.finish(200).expect("finish");
+        let mut decoder =
+            AnimationDecoder::with_options(&webp, ColorMode::BgraPremultiplied, false)
+                .expect("decoder");
+        decoder.set_scale(4, 4).expect("set_scale");
+        let frames = decoder.decode_all().expect("decode_all");
+        for px in frames[1].data.chunks(4) {
+            assert_eq!(px[3], 128);
+            for (c, expected) in [50u32, 100, 200].into_iter().enumerate() {
+                assert!(
+                    (px[c] as u32).abs_diff(expected * 128 / 255) <= 1,
+                    "{:?}",
+                    px
+                );
+            }
+        }
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        for result in [
+            decoder.set_crop(4, 0, 5, 8),
+            decoder.set_crop(0, 0, 0, 8),
+            decoder.set_scale(0, 0),
+            decoder.set_scale(20000, 0),
+        ] {
+            expect_invalid(result);
+        }
+    }
+
+    #[test]
+    fn test_animation_resample() {
+        use webpx::{
+            resample_animation, AnimationDecoder, ColorMode, EncoderConfig, FrameIter,
+            ResampleOptions,
+        };
+
+        // 10 frames of 50ms
+        let webp = encode_moving_block_animation(4);
+        let full = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        let config = EncoderConfig::new().lossless(true).exact(true);
+        let resample = |options: &ResampleOptions| {
+            let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+            resample_animation(&mut decoder, options, &config).expect("resample")
+        };
+
+        // 10 fps keeps every other frame, shown twice as long
+        let resampled = resample(&ResampleOptions::new().max_fps(10.0));
+        let durations: Vec<_> = FrameIter::new(&resampled)
+            .expect("iter")
+            .map(|f| f.duration_ms)
+            .collect();
+        assert_eq!(durations, [100; 5]);
+        let frames = AnimationDecoder::new(&resampled)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.data, full[i * 2].data, "frame {}", i);
+        }
+
+        // A frame budget spreads the kept frames over the timeline
+        let resampled = resample(&ResampleOptions::new().max_frames(3));
+        let durations: Vec<_> = FrameIter::new(&resampled)
+            .expect("iter")
+            .map(|f| f.duration_ms)
+            .collect();
+        assert_eq!(durations, [200, 200, 100]);
+
+        // Both limits, plus the decoder's scaling
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        decoder.set_scale(16, 16).expect("set_scale");
+        let options = ResampleOptions::new().max_fps(30.0).max_frames(4);
+        let resampled = resample_animation(&mut decoder, &options, &config).expect("resample");
+        let info = AnimationDecoder::new(&resampled)
+            .expect("decoder")
+            .info()
+            .clone();
+        assert_eq!((info.width, info.height), (16, 16));
+        assert!(info.frame_count <= 4);
+        let total: u32 = FrameIter::new(&resampled)
+            .expect("iter")
+            .map(|f| f.duration_ms)
+            .sum();
+        assert_eq!(total, 500);
+
+        // No limits keeps every frame
+        let resampled = resample(&ResampleOptions::new());
+        assert_eq!(FrameIter::new(&resampled).expect("iter").count(), 10);
+
+        for options in [
+            ResampleOptions::new().max_fps(0.0),
+            ResampleOptions::new().max_fps(f32::NAN),
+            ResampleOptions::new().max_frames(0),
+        ] {
+            let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+            expect_invalid(resample_animation(&mut decoder, &options, &config));
+        }
+        let mut decoder =
+            AnimationDecoder::with_options(&webp, ColorMode::RgbaPremultiplied, false)
+                .expect("decoder");
+        expect_invalid(resample_animation(
+            &mut decoder,
+            &ResampleOptions::new(),
+            &config,
+        ));
+    }
+
+    #[test]
+    fn test_animation_add_frame_rgb() {
+        use webpx::AnimationEncoder;
+
+        let width = 16;
+        let height = 16;
+        let frame_rgb = generate_rgb(width, height, 100, 150, 200);
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.add_frame_rgb(&frame_rgb, 0).expect("add frame rgb");
+        encoder
+            .add_frame_rgb(&frame_rgb, 100)
+            .expect("add frame rgb 2");
+
+        let webp = encoder.finish(200).expect("finish");
+        let info = ImageInfo::from_webp(&webp).expect("info");
+        // Animation with 2+ frames should be marked animated
+        // (single frame might not be)
+        assert_eq!(info.width, width);
+        assert_eq!(info.height, height);
+    }
+
+    #[test]
+    fn test_animation_decode_all_with_durations() {
+        use webpx::{AnimationDecoder, AnimationEncoder};
+
+        let width = 8;
+        let height = 8;
+        let frame1 = generate_rgba(width, height, 255, 0, 0, 255);
+        let frame2 = generate_rgba(width, height, 0, 255, 0, 255);
+        let frame3 = generate_rgba(width, height, 0, 0, 255, 255);
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.add_frame_rgba(&frame1, 0).expect("add 1");
+        encoder.add_frame_rgba(&frame2, 100).expect("add 2");
+        encoder.add_frame_rgba(&frame3, 200).expect("add 3");
+        let webp = encoder.finish(300).expect("finish");
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        let frames = decoder.decode_all().expect("decode_all");
+
+        assert_eq!(frames.len(), 3);
+        // Timestamps are reported as END times by libwebp
+        // Frame 1 ends at 0+duration, etc.
+        assert!(frames[0].timestamp_ms >= 0);
+        assert!(frames[1].timestamp_ms > frames[0].timestamp_ms);
+        assert!(frames[2].timestamp_ms > frames[1].timestamp_ms);
+    }
+
+    #[test]
+    fn test_animation_has_more_frames() {
+        use webpx::{AnimationDecoder, AnimationEncoder};
+
+        let width = 8;
+        let height = 8;
+        let frame = generate_rgba(width, height, 100, 100, 100, 255);
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.add_frame_rgba(&frame, 0).expect("add");
+        let webp = encoder.finish(100).expect("finish");
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        assert!(decoder.has_more_frames());
+
+        decoder.next_frame().expect("next").expect("frame");
+        assert!(!decoder.has_more_frames());
+    }
+
+    #[test]
+    fn test_animation_color_mode_rgba() {
+        use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
+
+        let width = 8;
+        let height = 8;
+        let frame1 = generate_rgba(width, height, 100, 150, 200, 255);
+        let frame2 = generate_rgba(width, height, 200, 150, 100, 255);
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.add_frame_rgba(&frame1, 0).expect("add");
+        encoder.add_frame_rgba(&frame2, 100).expect("add");
+        let webp = encoder.finish(200).expect("finish");
+
+        // Test RGBA mode
+        let mut decoder =
+            AnimationDecoder::with_options(&webp, ColorMode::Rgba, true).expect("decoder");
+        let decoded = decoder.next_frame().expect("next").expect("frame");
+        assert_eq!(decoded.width, width);
+        assert_eq!(decoded.height, height);
+    }
+
+    #[test]
+    fn test_animation_color_mode_bgra() {
+        use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
+
+        let width = 8;
+        let height = 8;
+        let frame1 = generate_rgba(width, height, 100, 150, 200, 255);
+        let frame2 = generate_rgba(width, height, 200, 150, 100, 255);
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.add_frame_rgba(&frame1, 0).expect("add");
+        encoder.add_frame_rgba(&frame2, 100).expect("add");
+        let webp = encoder.finish(200).expect("finish");
+
+        // Test BGRA mode
+        let mut decoder =
+            AnimationDecoder::with_options(&webp, ColorMode::Bgra, false).expect("decoder");
+        let decoded = decoder.next_frame().expect("next").expect("frame");
+        assert_eq!(decoded.width, width);
+        assert_eq!(decoded.height, height);
+    }
+
+    #[test]
+    fn test_animation_decoder_yuv_error() {
+        use webpx::{AnimationDecoder, AnimationEncoder, ColorMode};
+
+        let width = 8;
+        let height = 8;
+        let frame = generate_rgba(width, height, 100, 150, 200, 255);
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.add_frame_rgba(&frame, 0).expect("add");
+        let webp = encoder.finish(100).expect("finish");
+
+        // YUV modes not supported for animation decoder
+        let result = AnimationDecoder::with_options(&webp, ColorMode::Yuv420, true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_animation_invalid_frame_size() {
+        use webpx::AnimationEncoder;
+
+        let mut encoder = AnimationEncoder::new(100, 100).expect("encoder");
+        let small_frame = vec![0u8; 10];
+        let result = encoder.add_frame_rgba(&small_frame, 0);
+        assert!(result.is_err());
+
+        let result_rgb = encoder.add_frame_rgb(&small_frame, 0);
+        assert!(result_rgb.is_err());
+    }
+
+    #[cfg(feature = "icc")]
+    #[test]
+    fn test_animation_with_icc_profile() {
+        use webpx::AnimationEncoder;
+
+        let width = 8;
+        let height = 8;
+        let frame = generate_rgba(width, height, 100, 150, 200, 255);
+
+        // Minimal ICC profile header (zeros are not a valid ICC but libwebp accepts them)
+        let fake_icc = vec![0u8; 128];
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.set_icc_profile(fake_icc.clone());
+        encoder.add_frame_rgba(&frame, 0).expect("add");
+
+        // libwebp mux accepts arbitrary byte sequences as ICC data
+        let webp = encoder
+            .finish(100)
+            .expect("finish should succeed even with invalid ICC");
+
+        // Verify the ICC data was embedded
+        let extracted = webpx::get_icc_profile(&webp).expect("should extract ICC");
+        assert_eq!(extracted, Some(fake_icc), "ICC profile should round-trip");
+    }
+
+    #[cfg(feature = "icc")]
+    #[test]
+    fn test_animation_metadata_roundtrip() {
+        use webpx::{AnimationDecoder, AnimationEncoder};
+
+        let (width, height) = (8, 8);
+        let icc = vec![7u8; 128];
+        let exif = b"Exif\0\0MM\0*animated exif".to_vec();
+        let xmp = b"<x:xmpmeta><dc:rights>(c) webpx</dc:rights></x:xmpmeta>".to_vec();
+
+        let mut encoder = AnimationEncoder::new(width, height).expect("encoder");
+        encoder.set_icc_profile(icc.clone());
+        encoder.set_exif(exif.clone());
+        encoder.set_xmp(xmp.clone());
+        for (i, red) in [0u8, 255].into_iter().enumerate() {
+            let frame = generate_rgba(width, height, red, 0, 0, 255);
+            encoder.add_frame_rgba(&frame, i as i32 * 100).expect("add");
+        }
+        let webp = encoder.finish(200).expect("finish");
+
+        let decoder = AnimationDecoder::new(&webp).expect("decoder");
+        assert_eq!(decoder.info().frame_count, 2);
+        assert_eq!(decoder.icc_profile(), Some(icc.as_slice()));
+        assert_eq!(decoder.exif(), Some(exif.as_slice()));
+        assert_eq!(decoder.xmp(), Some(xmp.as_slice()));
+
+        let owned = AnimationDecoder::from_owned(webp.clone()).expect("owned decoder");
+        assert_eq!(owned.xmp(), Some(xmp.as_slice()));
+        assert_eq!(webpx::get_exif(&webp).expect("get_exif"), Some(exif));
+
+        let plain = webpx::remove_xmp(&webpx::remove_exif(&webp).expect("remove exif"))
+            .expect("remove xmp");
+        let decoder = AnimationDecoder::new(&plain).expect("decoder");
+        assert_eq!(decoder.icc_profile(), Some(icc.as_slice()));
+        assert_eq!(decoder.exif(), None);
+        assert_eq!(decoder.xmp(), None);
+    }
+}
+
+#[cfg(feature = "gif")]
+mod gif_tests {
+    use super::expect_invalid;
+    use webpx::{
+        gif_to_webp, webp_to_gif, AnimationDecoder, AnimationEncoderOptions, EncoderConfig, Error,
+    };
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn gif_frame(
+        left: u16,
+        top: u16,
+        size: u16,
+        index: u8,
+        delay: u16,
+        dispose: gif::DisposalMethod,
+    ) -> gif::Frame<'static> {
+        let mut pixels = vec![index; size as usize * size as usize];
+        // Index 3 is transparent
+        pixels[size as usize + 1] = 3;
+        let mut frame = gif::Frame::from_indexed_pixels(size, size, pixels, Some(3));
+        frame.left = left;
+        frame.top = top;
+        frame.delay = delay;
+        frame.dispose = dispose;
+        frame
+    }
+
+    /// 8x8 GIF exercising each disposal method, zero delay and a loop count.
+    fn test_gif() -> Vec<u8> {
+        let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0];
+        let mut data = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut data, 8, 8, &palette).expect("gif encoder");
+            encoder.set_repeat(gif::Repeat::Finite(2)).expect("repeat");
+            use gif::DisposalMethod::{Background, Keep, Previous};
+            for frame in [
+                gif_frame(0, 0, 8, 0, 5, Keep),
+                gif_frame(2, 2, 4, 1, 0, Background),
+                gif_frame(0, 0, 4, 2, 20, Previous),
+                gif_frame(6, 6, 2, 1, 10, Keep),
+            ] {
+                encoder.write_frame(&frame).expect("write frame");
+            }
+        }
+        data
+    }
+
+    fn pixel(data: &[u8], x: usize, y: usize) -> [u8; 4] {
+        let i = (y * 8 + x) * 4;
+        [data[i], data[i + 1], data[i + 2], data[i + 3]]
+    }
+
+    #[test]
+    fn test_gif_to_webp() {
+        let gif = test_gif();
+        let options = AnimationEncoderOptions::new().allow_mixed(false);
+        let webp = gif_to_webp(&gif[..], &options, &EncoderConfig::new().lossless(true))
+            .expect("gif_to_webp");
+
+        let mut decoder = AnimationDecoder::new(&webp).expect("decoder");
+        assert_eq!(decoder.info().width, 8);
+        assert_eq!(decoder.info().loop_count, 3);
+        assert_eq!(decoder.info().bgcolor, 0xffff_0000);
+
+        let frames = decoder.decode_all().expect("decode_all");
+        let timestamps: Vec<_> = frames.iter().map(|f| f.timestamp_ms).collect();
+        // 50ms, 0ms (forced to 100ms), 200ms, 100ms
+        assert_eq!(timestamps, [50, 150, 350, 450]);
+
+        assert_eq!(pixel(&frames[0].data, 0, 0), RED);
+
+        // Transparent GIF pixels leave the canvas unchanged
+        assert_eq!(pixel(&frames[1].data, 2, 2), GREEN);
+        assert_eq!(pixel(&frames[1].data, 3, 3), RED);
+
+        // Frame 1 was disposed to (transparent) background
+        assert_eq!(pixel(&frames[2].data, 0, 0), BLUE);
+        assert_eq!(pixel(&frames[2].data, 5, 5)[3], 0);
+        assert_eq!(pixel(&frames[2].data, 7, 7), RED);
+
+        // Frame 2 was disposed to the previous canvas
+        assert_eq!(pixel(&frames[3].data, 0, 0), RED);
+        assert_eq!(pixel(&frames[3].data, 3, 3)[3], 0);
+        assert_eq!(pixel(&frames[3].data, 6, 6), GREEN);
+    }
+
+    #[test]
+    fn test_gif_to_webp_identical_frames() {
+        let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0];
+        let mut gif = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut gif, 8, 8, &palette).expect("gif encoder");
+            encoder.set_repeat(gif::Repeat::Infinite).expect("repeat");
+            for _ in 0..2 {
+                let frame = gif_frame(0, 0, 8, 0, 10, gif::DisposalMethod::Keep);
+                encoder.write_frame(&frame).expect("write frame");
+            }
+        }
+
+        // libwebp merges the frames into a still image
+        let webp = gif_to_webp(
+            &gif[..],
+            &AnimationEncoderOptions::new(),
+            &EncoderConfig::new().lossless(true),
+        )
+        .expect("gif_to_webp");
+        let info = webpx::ImageInfo::from_webp(&webp).expect("info");
+        assert!(!info.has_animation);
+        assert_eq!((info.width, info.height), (8, 8));
+    }
+
+    #[test]
+    fn test_gif_to_webp_lossy() {
+        let gif = test_gif();
+        let options = AnimationEncoderOptions::new().minimize_size(true);
+        let webp = gif_to_webp(&gif[..], &options, &EncoderConfig::new().quality(75.0))
+            .expect("gif_to_webp");
+        let frames = AnimationDecoder::new(&webp)
+            .expect("decoder")
+            .decode_all()
+            .expect("decode_all");
+        assert_eq!(frames.len(), 4);
+    }
+
+    #[test]
+    fn test_gif_to_webp_invalid() {
+        let result = gif_to_webp(
+            &b"GIF89a not really"[..],
+            &AnimationEncoderOptions::new(),
+            &EncoderConfig::new(),
+        );
+        expect_invalid(result);
+
+        // A tiny file can declare a huge screen
+        let mut huge = Vec::new();
+        {
+            let palette = [0u8; 6];
+            let mut encoder =
+                gif::Encoder::new(&mut huge, 60000, 60000, &palette).expect("encoder");
+            encoder
+                .write_frame(&gif_frame(0, 0, 2, 0, 10, gif::DisposalMethod::Keep))
+                .expect("write frame");
+        }
+        let result = gif_to_webp(
+            &huge[..],
+            &AnimationEncoderOptions::new(),
+            &EncoderConfig::new(),
+        );
+        expect_invalid(result);
+    }
+
+    #[test]
+    fn test_gif_to_webp_stoppable() {
+        use webpx::{gif_to_webp_stoppable, Stop, StopReason, Unstoppable};
+
+        struct Cancelled;
+        impl Stop for Cancelled {
+            fn check(&self) -> Result<(), StopReason> {
+                Err(StopReason::Cancelled)
+            }
+        }
+
+        let options = AnimationEncoderOptions::new();
+        let config = EncoderConfig::new().lossless(true);
+        match gif_to_webp_stoppable(&test_gif()[..], &options, &config, Cancelled) {
+            Err(ref e) if matches!(e.error(), Error::Stopped(StopReason::Cancelled)) => {}
+            other => panic!("expected Stopped, got {:?}", other.map(|v| v.len())),
+        }
+        let webp = gif_to_webp_stoppable(&test_gif()[..], &options, &config, Unstoppable)
+            .expect("gif_to_webp_stoppable");
+        assert_eq!(
+            AnimationDecoder::new(&webp)
+                .expect("decoder")
+                .info()
+                .frame_count,
+            4
+        );
+    }
+
+    #[test]
+    fn test_webp_to_gif() {
+        let options = AnimationEncoderOptions::new().allow_mixed(false);
+        let webp = gif_to_webp(
+            &test_gif()[..],
+            &options,
+            &EncoderConfig::new().lossless(true),
+        )
+        .expect("gif_to_webp");
+        let gif = webp_to_gif(&webp).expect("webp_to_gif");
+
+        let mut decode_options = gif::DecodeOptions::new();
+        decode_options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = decode_options.read_info(&gif[..]).expect("gif decoder");
+        assert_eq!((decoder.width(), decoder.height()), (8, 8));
+        assert_eq!(decoder.repeat(), gif::Repeat::Finite(2));
+
+        let mut delays = Vec::new();
+        let mut first = None;
+        while let Some(frame) = decoder.read_next_frame().expect("gif frame") {
+            delays.push(frame.delay);
+            first.get_or_insert_with(|| frame.buffer.to_vec());
+        }
+        assert_eq!(delays, [5, 10, 20, 10]);
+        assert_eq!(pixel(&first.expect("first frame"), 0, 0), RED);
+    }
+
+    #[test]
+    fn test_webp_to_gif_keeps_total_duration() {
+        use webpx::AnimationEncoder;
+
+        // 30 frames of 33ms: rounding each delay alone would give 900ms
+        let mut encoder = AnimationEncoder::new(8, 8).expect("encoder");
+        encoder.set_lossless(true);
+        for i in 0..30u8 {
+            let frame: Vec<u8> = [i * 8, 0, 255 - i * 8, 255].repeat(64);
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 33)
+                .expect("add frame");
+        }
+        let webp = encoder.finish(990).expect("finish");
+        let gif = webp_to_gif(&webp).expect("webp_to_gif");
+
+        let mut decode_options = gif::DecodeOptions::new();
+        decode_options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = decode_options.read_info(&gif[..]).expect("gif decoder");
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().expect("gif frame") {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays.len(), 30);
+        assert_eq!(delays.iter().map(|&d| d as u32).sum::<u32>(), 99);
+        assert!(delays.iter().all(|&d| d == 3 || d == 4), "{:?}", delays);
+    }
+
+    #[test]
+    fn test_webp_to_gif_invalid() {
+        match webp_to_gif(b"not a webp") {
+            Err(_) => {}
+            Ok(_) => panic!("expected an error for invalid WebP"),
+        }
+    }
+}
+
+#[cfg(feature = "apng")]
+mod apng_tests {
+    use webpx::{webp_to_apng, AnimationEncoder};
+
+    #[test]
+    fn test_webp_to_apng() {
+        let mut encoder = AnimationEncoder::with_options(4, 4, false, 3).expect("encoder");
+        encoder.set_lossless(true);
+        let colors = [[255u8, 0, 0, 255], [0, 255, 0, 128], [0, 0, 255, 0]];
+        for (i, color) in colors.iter().enumerate() {
+            let frame = color.repeat(16);
+            encoder
+                .add_frame_rgba(&frame, i as i32 * 40)
+                .expect("add frame");
+        }
+        let webp = encoder.finish(200).expect("finish");
+
+        let apng = webp_to_apng(&webp).expect("webp_to_apng");
+        let decoder = png::Decoder::new(std::io::Cursor::new(&apng));
+        let mut reader = decoder.read_info().expect("png info");
+        let control = reader.info().animation_control.expect("acTL chunk");
+        assert_eq!(control.num_frames, 3);
+        assert_eq!(control.num_plays, 3);
+
+        let mut buf = vec![0; reader.output_buffer_size().expect("buffer size")];
+        let mut delays = Vec::new();
+        for color in &colors {
+            reader.next_frame(&mut buf).expect("png frame");
+            let fc = reader.info().frame_control.expect("fcTL chunk");
+            delays.push((fc.delay_num, fc.delay_den));
+            // Lossless frames come back exactly, including alpha
+            if color[3] != 0 {
+                assert_eq!(&buf[..4], color);
+            } else {
+                assert_eq!(buf[3], 0);
+            }
+        }
+        assert_eq!(delays, [(40, 1000), (40, 1000), (120, 1000)]);
     }
 }
 